@@ -0,0 +1,91 @@
+locale-name = Deutsch
+
+## Layout
+
+layout-no-account = Kein Konto
+layout-cape = Umhang
+layout-not-official = KEIN OFFIZIELLER MINECRAFT-DIENST. NICHT VON MOJANG ODER MICROSOFT GENEHMIGT ODER MIT IHNEN VERBUNDEN.
+
+## Metadata
+
+meta-description = PipeworkMC ist ein Minecraft-Server. Verwalte dein Konto, deinen Skin und verknüpfte Dienste im Dashboard.
+meta-description-log-in = Melde dich mit deinem Microsoft-Konto bei PipeworkMC an.
+
+## Navigation
+
+nav-home = Startseite
+nav-main = Hauptnavigation
+nav-breadcrumbs = Brotkrümelnavigation
+
+## Landing
+
+landing-tagline = Ein von Grund auf neu gebauter Minecraft-Server, mit Konto-Dashboard und Werkzeugen im Browser.
+landing-open-dashboard = Dashboard öffnen
+landing-status = Serverstatus
+landing-status-unknown = Status unbekannt
+landing-status-online = Online
+landing-status-offline = Offline
+landing-status-players = { $online } / { $max } Spieler
+landing-status-latency = { $ms } ms
+landing-status-last-seen = Zuletzt online { $time }
+landing-status-address = Adresse:
+landing-feature-dashboard = Konto-Dashboard
+landing-feature-dashboard-body = Melde dich mit deinem Microsoft-Konto an, um Skin und Umhang zu sehen, dein GitHub-Konto zu verknüpfen und ein Design zu wählen.
+landing-feature-editor = Code-Editor
+landing-feature-editor-body = Schreibe und teste Skripte für den Server direkt im Browser.
+landing-coming-soon = Demnächst
+landing-feature-community = Community
+landing-feature-community-body = Verfolge die Entwicklung, melde Probleme und sprich mit dem Team.
+landing-community-github = GitHub
+landing-community-matrix = Matrix
+landing-community-email = E-Mail
+
+## Dashboard
+
+dashboard = Dashboard
+dashboard-overview = Übersicht
+dashboard-log-in = Anmelden
+dashboard-skin-front = Skin (vorne)
+dashboard-skin-back = Skin (hinten)
+dashboard-cape = Umhang
+dashboard-linked-accounts = { $count ->
+    [0] Keine verknüpften Konten
+    [one] { $count } verknüpftes Konto
+   *[other] { $count } verknüpfte Konten
+}
+dashboard-unlink = { $account } trennen
+dashboard-github-maintainer = Maintainer von { $org }
+dashboard-theme = Design
+dashboard-theme-system = System
+dashboard-theme-light = Hell
+dashboard-theme-dark = Dunkel
+dashboard-language = Sprache
+dashboard-language-automatic = Automatisch
+dashboard-save = Speichern
+
+## Development log in
+
+dev-log-in = Entwicklungs-Anmeldung
+dev-log-in-username = Benutzername
+dev-log-in-uuid = UUID (leer für Offline-UUID)
+
+## Flash messages
+
+flash-log-in-required = Melde dich an, um diese Seite zu sehen.
+flash-already-logged-in = Du bist bereits angemeldet.
+flash-logged-in = Angemeldet als { $username }.
+flash-github-linked = GitHub-Konto { $account } verknüpft.
+flash-github-unlinked = GitHub-Konto { $account } getrennt.
+flash-github-invitation-pending = Deine Einladung in die GitHub-Organisation { $org } steht noch aus. Nimm sie an und verknüpfe erneut, um Maintainer-Zugriff zu erhalten.
+flash-github-not-linked = Es ist kein GitHub-Konto verknüpft.
+flash-settings-saved = Einstellungen gespeichert.
+
+## Login
+
+login-error-xsts = Xbox-Authentifizierungstoken konnte nicht gegen ein XSTS-Minecraft-Token getauscht werden: { $reason }
+login-xsts-banned = Konto ist von Xbox gesperrt
+login-xsts-no-xbox = Konto hat kein Xbox-Profil
+login-xsts-unavailable-country = Xbox ist im Land des Kontos nicht verfügbar
+login-xsts-adult-verification = Konto benötigt eine Altersverifizierung bei Xbox (Südkorea)
+login-xsts-underage = Konto ist minderjährig und muss einer Familie hinzugefügt werden
+login-xsts-unknown = Unbekannter Fehler
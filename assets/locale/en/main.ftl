@@ -0,0 +1,91 @@
+locale-name = English
+
+## Layout
+
+layout-no-account = No Account
+layout-cape = Cape
+layout-not-official = NOT AN OFFICIAL MINECRAFT SERVICE. NOT APPROVED BY OR ASSOCIATED WITH MOJANG OR MICROSOFT.
+
+## Metadata
+
+meta-description = PipeworkMC is a Minecraft server. Manage your account, skin and linked services from the dashboard.
+meta-description-log-in = Log in to PipeworkMC with your Microsoft account.
+
+## Navigation
+
+nav-home = Home
+nav-main = Main
+nav-breadcrumbs = Breadcrumbs
+
+## Landing
+
+landing-tagline = A Minecraft server built from the ground up, with an account dashboard and in-browser tools.
+landing-open-dashboard = Open Dashboard
+landing-status = Server Status
+landing-status-unknown = Status unknown
+landing-status-online = Online
+landing-status-offline = Offline
+landing-status-players = { $online } / { $max } players
+landing-status-latency = { $ms } ms
+landing-status-last-seen = Last seen online { $time }
+landing-status-address = Address:
+landing-feature-dashboard = Account Dashboard
+landing-feature-dashboard-body = Log in with your Microsoft account to see your skin and cape, link your GitHub account and choose a theme.
+landing-feature-editor = Code Editor
+landing-feature-editor-body = Write and test scripts for the server right in your browser.
+landing-coming-soon = Coming soon
+landing-feature-community = Community
+landing-feature-community-body = Follow development, report issues and talk to the team.
+landing-community-github = GitHub
+landing-community-matrix = Matrix
+landing-community-email = Email
+
+## Dashboard
+
+dashboard = Dashboard
+dashboard-overview = Overview
+dashboard-log-in = Log In
+dashboard-skin-front = Skin (front)
+dashboard-skin-back = Skin (back)
+dashboard-cape = Cape
+dashboard-linked-accounts = { $count ->
+    [0] No Linked Accounts
+    [one] { $count } Linked Account
+   *[other] { $count } Linked Accounts
+}
+dashboard-unlink = Unlink { $account }
+dashboard-github-maintainer = Maintainer of { $org }
+dashboard-theme = Theme
+dashboard-theme-system = System
+dashboard-theme-light = Light
+dashboard-theme-dark = Dark
+dashboard-language = Language
+dashboard-language-automatic = Automatic
+dashboard-save = Save
+
+## Development log in
+
+dev-log-in = Development Log In
+dev-log-in-username = Username
+dev-log-in-uuid = UUID (blank for offline UUID)
+
+## Flash messages
+
+flash-log-in-required = Log in to see that page.
+flash-already-logged-in = You are already logged in.
+flash-logged-in = Logged in as { $username }.
+flash-github-linked = Linked GitHub account { $account }.
+flash-github-unlinked = Unlinked GitHub account { $account }.
+flash-github-not-linked = No GitHub account is linked.
+flash-github-invitation-pending = Your invitation to the { $org } GitHub organisation is still pending. Accept it and link again to get maintainer access.
+flash-settings-saved = Settings saved.
+
+## Login
+
+login-error-xsts = Failed to exchange XBOX auth token for XSTS Minecraft token: { $reason }
+login-xsts-banned = Account is banned from Xbox
+login-xsts-no-xbox = Account does not have an Xbox profile
+login-xsts-unavailable-country = Xbox is unavailable in account country
+login-xsts-adult-verification = Account needs adult verification on Xbox (South Korea)
+login-xsts-underage = Account is underage and needs to be added to a Family.
+login-xsts-unknown = Unknown error
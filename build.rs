@@ -1,6 +1,91 @@
-use std::env;
+use std::{
+    env,
+    fs,
+    io::Write,
+    path::{ Path, PathBuf }
+};
+use flate2::{
+    Compression,
+    write::GzEncoder
+};
+
+
+const COMPRESSIBLE_EXTENSIONS : &[&str] = &[ "css", "svg", "js", "json", "txt", "html", "xml" ];
 
 
 fn main() {
-    println!("cargo::rustc-env=CRATE_ROOT={}", env::current_dir().unwrap().display())
+    let crate_root = env::current_dir().unwrap();
+    println!("cargo::rustc-env=CRATE_ROOT={}", crate_root.display());
+
+    // Every file under `assets/` is embedded, so release builds serve assets without touching the disk.
+    let assets_root = crate_root.join("assets");
+    println!("cargo::rerun-if-changed={}", assets_root.display());
+    let mut assets = Vec::new();
+    collect_assets(&assets_root, &mut assets);
+    assets.sort();
+
+    let out_dir         = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let precompress_dir = out_dir.join("precompressed");
+    let mut manifest    = String::from("pub static EMBEDDED_ASSETS : &[EmbeddedAsset] = &[\n");
+    for asset in assets {
+        let path = asset.strip_prefix(&assets_root).unwrap().components()
+            .map(|component| component.as_os_str().to_str().unwrap())
+            .collect::<Vec<_>>().join("/");
+        let (gzip, brotli) = if (asset.extension().and_then(|extension| extension.to_str()).is_some_and(|extension| COMPRESSIBLE_EXTENSIONS.contains(&extension))) {
+            let bytes = fs::read(&asset).unwrap();
+            (
+                precompress(&precompress_dir, &path, "gz", bytes.len(), gzip(&bytes)),
+                precompress(&precompress_dir, &path, "br", bytes.len(), brotli(&bytes))
+            )
+        } else { (None, None) };
+        manifest.push_str(&format!(
+            "    EmbeddedAsset {{ path : {path:?}, bytes : include_bytes!({:?}), gzip : {}, brotli : {} }},\n",
+            asset.display().to_string(),
+            include_option(gzip),
+            include_option(brotli)
+        ));
+    }
+    manifest.push_str("];\n");
+    fs::write(out_dir.join("assets.rs"), manifest).unwrap();
+}
+
+
+fn collect_assets(dir : &Path, assets : &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if (path.is_dir()) {
+            collect_assets(&path, assets);
+        } else {
+            assets.push(path);
+        }
+    }
+}
+
+
+fn gzip(bytes : &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn brotli(bytes : &[u8]) -> Vec<u8> {
+    let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 11, 22);
+    encoder.write_all(bytes).unwrap();
+    encoder.into_inner()
+}
+
+// Only keeps the compressed variant if it is actually smaller than the original.
+fn precompress(dir : &Path, path : &str, extension : &str, original_len : usize, compressed : Vec<u8>) -> Option<PathBuf> {
+    if (compressed.len() >= original_len) { return None; }
+    let out = dir.join(format!("{path}.{extension}"));
+    fs::create_dir_all(out.parent().unwrap()).unwrap();
+    fs::write(&out, compressed).unwrap();
+    Some(out)
+}
+
+fn include_option(path : Option<PathBuf>) -> String {
+    match (path) {
+        Some(path) => format!("Some(include_bytes!({:?}))", path.display().to_string()),
+        None       => "None".to_string()
+    }
 }
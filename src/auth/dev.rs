@@ -0,0 +1,115 @@
+use crate::{
+    auth::{
+        minecraft::account,
+        provider::{ AuthIdentity, AuthProvider, AuthProviderKind }
+    },
+    i18n::{ Locale, t },
+    layout::{ self, component::{ Button, ButtonStyle, Centre, Field, Form, Input } },
+    site::{ self, SharedSiteState },
+    util::dotenv
+};
+use std::path::Path;
+use tide::{
+    Request,
+    Response,
+    StatusCode,
+    utils::async_trait
+};
+use serde::Deserialize as Deser;
+use uuid::{ Builder, Uuid };
+use md5::{ Digest, Md5 };
+
+
+pub fn is_enabled() -> bool {
+    dotenv::try_var("DEV_LOGIN").is_some_and(|v| v.trim() == "true")
+}
+
+
+pub fn verify_environment() {
+    if (dotenv::try_var("ENVIRONMENT").is_some_and(|v| v.trim().eq_ignore_ascii_case("production"))) {
+        panic!("refusing to start with DEV_LOGIN enabled in a production environment");
+    }
+    if (Path::new("cert/pipeworkmc.cert").exists() || Path::new("cert/pipeworkmc.key").exists()) {
+        panic!("refusing to start with DEV_LOGIN enabled while a TLS certificate is configured");
+    }
+}
+
+
+pub fn is_valid_username(username : &str) -> bool {
+    username.len() >= 3 && account::is_valid_username(username)
+}
+
+pub fn offline_uuid(username : &str) -> Uuid {
+    let digest = Md5::digest(format!("OfflinePlayer:{username}").as_bytes());
+    Builder::from_md5_bytes(digest.into()).into_uuid()
+}
+
+
+pub struct DevAuthProvider;
+
+#[derive(Deser)]
+struct DevLoginForm {
+    username : String,
+    uuid     : String
+}
+
+#[async_trait]
+impl AuthProvider for DevAuthProvider {
+
+    fn id(&self) -> &'static str { "dev" }
+
+    fn name(&self, locale : Locale) -> String { t!(locale, "dev-log-in") }
+
+    fn kind(&self) -> AuthProviderKind { AuthProviderKind::Login }
+
+    async fn start(&self, req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+        let locale = site::locale(req);
+        let action = format!("/auth/{}/callback", self.id());
+        let form   = Form {
+            action : &action,
+            csrf   : layout::csrf_field!(req),
+            fields : vec![
+                Field {
+                    name  : "username",
+                    label : Some(t!(locale, "dev-log-in-username")),
+                    input : Input::Text { value : None, required : true, min_length : Some(3), max_length : Some(16), pattern : Some("[A-Za-z0-9_]+") },
+                    error : None
+                },
+                Field {
+                    name  : "uuid",
+                    label : Some(t!(locale, "dev-log-in-uuid")),
+                    input : Input::Text { value : None, required : false, min_length : None, max_length : None, pattern : None },
+                    error : None
+                }
+            ],
+            submit : Button::new(t!(locale, "dashboard-log-in"), ButtonStyle::Primary),
+            inline : false
+        };
+        Ok(tide::Response::from(layout::default(req,
+            layout::PageType::Normal,
+            None,
+            layout::PageMeta::new(t!(locale, "dashboard"), t!(locale, "dev-log-in")).robots(layout::Robots::NoIndex),
+            Centre(form)
+        ).await))
+    }
+
+    async fn callback(&self, req : &mut Request<SharedSiteState>) -> tide::Result<AuthIdentity> {
+        let form     = req.body_form::<DevLoginForm>().await?;
+        let username = form.username.trim();
+        if (! is_valid_username(username)) {
+            return Err(tide::Error::from_str(StatusCode::BadRequest, "Failed to log in: Invalid username"));
+        }
+        let uuid = form.uuid.trim();
+        let uuid = if (uuid.is_empty()) { offline_uuid(username) } else {
+            Uuid::parse_str(uuid).map_err(|_| tide::Error::from_str(StatusCode::BadRequest, "Failed to log in: Invalid UUID"))?
+        };
+        Ok(AuthIdentity::Minecraft {
+            uuid,
+            username : username.to_string(),
+            skin     : None,
+            texture  : None,
+            cape     : None
+        })
+    }
+
+}
@@ -0,0 +1,459 @@
+use crate::{
+    auth::provider::{ self, AuthIdentity, AuthProvider, AuthProviderKind },
+    i18n::Locale,
+    layout,
+    site::SharedSiteState,
+    util::{ dotenv, rand }
+};
+#[cfg(feature = "account-db")]
+use pipeworkmc_db::GithubAccount;
+use surf::{ Client, Body };
+use tide::{
+    Request,
+    Response,
+    StatusCode,
+    utils::async_trait
+};
+use urlencoding::encode as urlencode;
+use serde::Serialize as Ser;
+use serde::Deserialize as Deser;
+use sha2::{ Digest, Sha256 };
+use base64::{
+    prelude::BASE64_URL_SAFE_NO_PAD,
+    Engine
+};
+
+
+const GITHUB_SCOPE      : &str = "read:user read:org repo";
+const GITHUB_USER_AGENT : &str = "pipeworkmc-webserver";
+
+
+fn github_oauth_base() -> &'static str {
+    dotenv::try_var("GITHUB_OAUTH_BASE").unwrap_or("https://github.com")
+}
+
+fn github_api_base() -> &'static str {
+    dotenv::try_var("GITHUB_API_BASE").unwrap_or("https://api.github.com")
+}
+
+
+pub fn build_pkce_challenge(verifier : &str) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+pub fn build_github_authorize_url(state : &str, pkce_verifier : &str) -> String {
+    let base         = github_oauth_base();
+    let client_id    = dotenv::var("GITHUB_CLIENT_ID");
+    let redirect_uri = urlencode(dotenv::var("GITHUB_REDIRECT_URI"));
+    let scope        = urlencode(GITHUB_SCOPE);
+    let state        = urlencode(state);
+    let challenge    = build_pkce_challenge(pkce_verifier);
+    format!("{base}/login/oauth/authorize?client_id={client_id}&redirect_uri={redirect_uri}&scope={scope}&state={state}&code_challenge={challenge}&code_challenge_method=S256&allow_signup=false")
+}
+
+
+pub async fn exchange_github_token(
+    client        : &Client,
+    github_code   : &str,
+    pkce_verifier : &str
+) -> surf::Result<GithubAccessToken> {
+    let request = client.post(format!("{}/login/oauth/access_token", github_oauth_base()))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "application/json")
+        .header("User-Agent", GITHUB_USER_AGENT)
+        .body(Body::from_form(&GithubTokenQuery {
+            client_id     : dotenv::var("GITHUB_CLIENT_ID"),
+            client_secret : dotenv::var("GITHUB_CLIENT_SECRET"),
+            code          : github_code,
+            redirect_uri  : dotenv::var("GITHUB_REDIRECT_URI"),
+            code_verifier : pkce_verifier
+        })?);
+    let mut response = request.send().await.map_err(|err| {
+        surf::Error::from_str(err.status(), format!("Failed to exchange GitHub auth code for GitHub access token: {}", err.into_inner()))
+    })?;
+    let status = response.status();
+    if (! status.is_success()) {
+        return Err(surf::Error::from_str(status, format!("Failed to exchange GitHub auth code for GitHub access token: {}", status.canonical_reason())));
+    }
+    // GitHub reports token exchange errors with a 200 status.
+    match (response.body_json::<GithubAccessTokenDeser>().await?) {
+        GithubAccessTokenDeser::Ok(token)  => Ok(token),
+        GithubAccessTokenDeser::Err(error) => Err(surf::Error::from_str(StatusCode::Unauthorized, format!("Failed to exchange GitHub auth code for GitHub access token: {}",
+            error.error_description.as_ref().unwrap_or(&error.error)
+        )))
+    }
+}
+
+#[derive(Ser)]
+struct GithubTokenQuery<'l> {
+    client_id     : &'static str,
+    client_secret : &'static str,
+    code          : &'l str,
+    redirect_uri  : &'static str,
+    code_verifier : &'l str
+}
+
+#[derive(Deser)]
+#[serde(untagged)]
+enum GithubAccessTokenDeser {
+    Ok(GithubAccessToken),
+    Err(GithubAccessTokenError)
+}
+
+#[derive(Deser)]
+struct GithubAccessTokenError {
+    error             : String,
+    error_description : Option<String>
+}
+
+#[derive(Deser)]
+pub struct GithubAccessToken {
+    pub access_token : String,
+    pub scope        : String
+}
+
+
+pub async fn fetch_github_user(
+    client       : &Client,
+    github_token : &str
+) -> surf::Result<GithubUser> {
+    let request = client.get(format!("{}/user", github_api_base()))
+        .header("Authorization", format!("Bearer {github_token}"))
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", GITHUB_USER_AGENT);
+    let mut response = request.send().await.map_err(|err| {
+        surf::Error::from_str(err.status(), format!("Failed to fetch GitHub user: {}", err.into_inner()))
+    })?;
+    let status = response.status();
+    if (! status.is_success()) {
+        return Err(surf::Error::from_str(status, format!("Failed to fetch GitHub user: {}", status.canonical_reason())));
+    }
+    response.body_json::<GithubUser>().await
+}
+
+#[derive(Deser, Clone, Debug)]
+pub struct GithubUser {
+    pub id    : u64,
+    pub login : String
+}
+
+
+pub async fn fetch_github_org_membership(
+    client       : &Client,
+    github_token : &str,
+    org          : &str
+) -> surf::Result<GithubMembershipState> {
+    let request = client.get(format!("{}/user/memberships/orgs/{}", github_api_base(), urlencode(org)))
+        .header("Authorization", format!("Bearer {github_token}"))
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", GITHUB_USER_AGENT);
+    fetch_github_membership(request, "organisation").await
+}
+
+pub async fn fetch_github_team_membership(
+    client       : &Client,
+    github_token : &str,
+    org          : &str,
+    team_slug    : &str,
+    login        : &str
+) -> surf::Result<GithubMembershipState> {
+    let request = client.get(format!("{}/orgs/{}/teams/{}/memberships/{}", github_api_base(), urlencode(org), urlencode(team_slug), urlencode(login)))
+        .header("Authorization", format!("Bearer {github_token}"))
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", GITHUB_USER_AGENT);
+    fetch_github_membership(request, "team").await
+}
+
+async fn fetch_github_membership(request : surf::RequestBuilder, kind : &str) -> surf::Result<GithubMembershipState> {
+    let mut response = request.send().await.map_err(|err| {
+        surf::Error::from_str(err.status(), format!("Failed to fetch GitHub {kind} membership: {}", err.into_inner()))
+    })?;
+    let status = response.status();
+    if (status == StatusCode::NotFound || status == StatusCode::Forbidden) {
+        return Ok(GithubMembershipState::None);
+    }
+    if (! status.is_success()) {
+        return Err(surf::Error::from_str(status, format!("Failed to fetch GitHub {kind} membership: {}", status.canonical_reason())));
+    }
+    Ok(response.body_json::<GithubMembership>().await?.state)
+}
+
+#[derive(Deser)]
+struct GithubMembership {
+    state : GithubMembershipState
+}
+
+#[derive(Deser, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GithubMembershipState {
+    #[serde(rename = "active")]
+    Active,
+    // Invited, but the invitation hasn't been accepted yet.
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(skip)]
+    None
+}
+
+
+pub fn maintainer_org() -> Option<&'static str> {
+    dotenv::try_var("GITHUB_MAINTAINER_ORG").map(str::trim).filter(|org| ! org.is_empty())
+}
+
+// Maintainers are the members of `GITHUB_MAINTAINER_ORG`, narrowed to `GITHUB_MAINTAINER_TEAM` when it is set.
+pub async fn fetch_github_maintainer_membership(
+    client       : &Client,
+    github_token : &str,
+    login        : &str
+) -> surf::Result<GithubMembershipState> {
+    let Some(org) = maintainer_org() else {
+        return Ok(GithubMembershipState::None);
+    };
+    match (dotenv::try_var("GITHUB_MAINTAINER_TEAM").map(str::trim).filter(|team_slug| ! team_slug.is_empty())) {
+        Some(team_slug) => fetch_github_team_membership(client, github_token, org, team_slug, login).await,
+        None            => fetch_github_org_membership(client, github_token, org).await
+    }
+}
+
+// GitHub reports granted scopes comma separated, and users can grant fewer than were requested.
+pub fn missing_github_scopes(granted : &str) -> Vec<&'static str> {
+    let granted = granted.split(',').map(str::trim).collect::<Vec<_>>();
+    GITHUB_SCOPE.split(' ').filter(|scope| ! granted.contains(scope)).collect()
+}
+
+
+#[derive(Debug)]
+pub struct GithubLink {
+    pub user         : GithubUser,
+    // The token is only kept to be stored with the link.
+    #[cfg(feature = "account-db")]
+    pub access_token : String,
+    #[cfg(feature = "account-db")]
+    pub scope        : String,
+    pub maintainer   : GithubMembershipState
+}
+
+impl GithubLink {
+
+    pub fn is_maintainer(&self) -> bool {
+        self.maintainer == GithubMembershipState::Active
+    }
+
+    #[cfg(feature = "account-db")]
+    pub fn to_record(&self) -> GithubAccount {
+        GithubAccount {
+            github_id    : self.user.id,
+            github_login : self.user.login.clone(),
+            access_token : self.access_token.clone(),
+            scope        : self.scope.clone(),
+            maintainer   : self.is_maintainer()
+        }
+    }
+
+}
+
+// Pending invitations are only reported while linking, so they aren't stored.
+#[cfg(feature = "account-db")]
+impl From<GithubAccount> for GithubLink {
+    fn from(record : GithubAccount) -> Self {
+        Self {
+            user         : GithubUser { id : record.github_id, login : record.github_login },
+            access_token : record.access_token,
+            scope        : record.scope,
+            maintainer   : if (record.maintainer) { GithubMembershipState::Active } else { GithubMembershipState::None }
+        }
+    }
+}
+
+
+// Links would be lost on restart without somewhere to keep them.
+pub fn is_enabled() -> bool {
+    cfg!(feature = "account-db") && dotenv::try_var("GITHUB_CLIENT_ID").is_some()
+}
+
+
+pub struct GithubAuthProvider;
+
+#[derive(Deser)]
+struct GithubOauthQuery {
+    #[serde(rename = "code")]
+    github_code : String,
+    state       : String
+}
+
+#[async_trait]
+impl AuthProvider for GithubAuthProvider {
+
+    fn id(&self) -> &'static str { "github" }
+
+    fn name(&self, _locale : Locale) -> String { "GitHub".to_string() }
+
+    fn kind(&self) -> AuthProviderKind { AuthProviderKind::Link }
+
+    fn icon(&self) -> layout::PreEscaped<String> {
+        layout::icon_svg!("brand/github.svg")
+    }
+
+    async fn start(&self, req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+        let state         = provider::begin_oauth_state(req, self.id());
+        let pkce_verifier = rand::gen_pkce_verifier();
+        let url           = build_github_authorize_url(&state, &pkce_verifier);
+        req.session_mut().insert_raw("github-pkce-verifier", pkce_verifier);
+        Ok(tide::Redirect::see_other(url).into())
+    }
+
+    async fn callback(&self, req : &mut Request<SharedSiteState>) -> tide::Result<AuthIdentity> {
+        let query = req.query::<GithubOauthQuery>()?;
+        provider::take_oauth_state(req, self.id(), &query.state)?;
+        let pkce_verifier = {
+            let session = req.session_mut();
+            let pkce    = session.get_raw("github-pkce-verifier");
+            session.remove("github-pkce-verifier");
+            pkce
+        }.ok_or_else(|| tide::Error::from_str(StatusCode::BadRequest, "Failed to link GitHub account: No link in progress"))?;
+
+        let client = Client::new();
+        let github_token = exchange_github_token(&client, &query.github_code, &pkce_verifier).await?;
+        let missing      = missing_github_scopes(&github_token.scope);
+        if (! missing.is_empty()) {
+            return Err(tide::Error::from_str(StatusCode::Forbidden, format!("Failed to link GitHub account: Missing scopes {}", missing.join(", "))));
+        }
+        let github_user = fetch_github_user(&client, &github_token.access_token).await?;
+        let maintainer  = fetch_github_maintainer_membership(&client, &github_token.access_token, &github_user.login).await?;
+
+        Ok(AuthIdentity::Github(GithubLink {
+            user         : github_user,
+            #[cfg(feature = "account-db")]
+            access_token : github_token.access_token,
+            #[cfg(feature = "account-db")]
+            scope        : github_token.scope,
+            maintainer
+        }))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tide::Body;
+
+    // A stand-in for both github.com and api.github.com. Code `good` exchanges for token `token`.
+    async fn mock_github() -> String {
+        let mut app = tide::new();
+        app.at("/login/oauth/access_token").post(|mut req : tide::Request<()>| async move {
+            let form = req.body_string().await?;
+            Ok(if (form.split('&').any(|pair| pair == "code=good")) {
+                Body::from_json(&serde_json::json!({ "access_token" : "token", "scope" : "read:org,read:user,repo", "token_type" : "bearer" }))?
+            } else {
+                Body::from_json(&serde_json::json!({ "error" : "bad_verification_code", "error_description" : "The code passed is incorrect or expired." }))?
+            })
+        });
+        app.at("/user").get(|req : tide::Request<()>| async move {
+            if (req.header("Authorization").is_none_or(|auth| auth.last().as_str() != "Bearer token")) {
+                return Ok(Response::new(StatusCode::Unauthorized));
+            }
+            Ok(Body::from_json(&serde_json::json!({ "id" : 1, "login" : "octocat" }))?.into())
+        });
+        app.at("/user/memberships/orgs/:org").get(|req : tide::Request<()>| async move {
+            Ok(match (req.param("org")?) {
+                "active"  => Body::from_json(&serde_json::json!({ "state" : "active" }))?.into(),
+                "pending" => Body::from_json(&serde_json::json!({ "state" : "pending" }))?.into(),
+                _         => Response::new(StatusCode::NotFound)
+            })
+        });
+        app.at("/orgs/:org/teams/:team/memberships/:login").get(|req : tide::Request<()>| async move {
+            Ok(if (req.param("team")? == "maintainers" && req.param("login")? == "octocat") {
+                Body::from_json(&serde_json::json!({ "state" : "active" }))?.into()
+            } else {
+                Response::new(StatusCode::NotFound)
+            })
+        });
+        app.at("/unavailable").get(|_| async { Ok(Response::new(StatusCode::ServiceUnavailable)) });
+
+        let mut listener = tide::listener::ToListener::<()>::to_listener("127.0.0.1:0").unwrap();
+        tide::listener::Listener::bind(&mut listener, app).await.unwrap();
+        let base = tide::listener::Listener::info(&listener)[0].connection().to_string();
+        smol::spawn(async move { _ = tide::listener::Listener::accept(&mut listener).await; }).detach();
+
+        dotenv::set_for_test("GITHUB_OAUTH_BASE", &base);
+        dotenv::set_for_test("GITHUB_API_BASE", &base);
+        dotenv::set_for_test("GITHUB_CLIENT_ID", "client-id");
+        dotenv::set_for_test("GITHUB_CLIENT_SECRET", "client-secret");
+        dotenv::set_for_test("GITHUB_REDIRECT_URI", "http://localhost/auth/github/callback");
+        base
+    }
+
+    #[test]
+    fn exchanges_code_for_token() { smol::block_on(async {
+        mock_github().await;
+        let token = exchange_github_token(&Client::new(), "good", "verifier").await.unwrap();
+        assert_eq!(token.access_token, "token");
+        assert!(missing_github_scopes(&token.scope).is_empty());
+    }) }
+
+    #[test]
+    fn rejects_bad_code_reported_with_ok_status() { smol::block_on(async {
+        mock_github().await;
+        let err = exchange_github_token(&Client::new(), "bad", "verifier").await.map(|_| ()).unwrap_err();
+        assert_eq!(err.status(), StatusCode::Unauthorized);
+        assert!(err.to_string().contains("incorrect or expired"));
+    }) }
+
+    #[test]
+    fn fetches_user() { smol::block_on(async {
+        mock_github().await;
+        let user = fetch_github_user(&Client::new(), "token").await.unwrap();
+        assert_eq!((user.id, user.login.as_str()), (1, "octocat"));
+        assert_eq!(fetch_github_user(&Client::new(), "revoked").await.unwrap_err().status(), StatusCode::Unauthorized);
+    }) }
+
+    #[test]
+    fn reads_org_membership() { smol::block_on(async {
+        mock_github().await;
+        let client = Client::new();
+        assert_eq!(fetch_github_org_membership(&client, "token", "active").await.unwrap(), GithubMembershipState::Active);
+        assert_eq!(fetch_github_org_membership(&client, "token", "pending").await.unwrap(), GithubMembershipState::Pending);
+        assert_eq!(fetch_github_org_membership(&client, "token", "other").await.unwrap(), GithubMembershipState::None);
+    }) }
+
+    #[test]
+    fn reads_maintainer_membership() { smol::block_on(async {
+        mock_github().await;
+        let client = Client::new();
+        assert_eq!(fetch_github_maintainer_membership(&client, "token", "octocat").await.unwrap(), GithubMembershipState::None);
+        dotenv::set_for_test("GITHUB_MAINTAINER_ORG", "active");
+        assert_eq!(fetch_github_maintainer_membership(&client, "token", "octocat").await.unwrap(), GithubMembershipState::Active);
+        dotenv::set_for_test("GITHUB_MAINTAINER_TEAM", "maintainers");
+        assert_eq!(fetch_github_maintainer_membership(&client, "token", "octocat").await.unwrap(), GithubMembershipState::Active);
+        assert_eq!(fetch_github_maintainer_membership(&client, "token", "someone").await.unwrap(), GithubMembershipState::None);
+    }) }
+
+    #[test]
+    fn reports_membership_errors() { smol::block_on(async {
+        let base = mock_github().await;
+        let request = Client::new().get(format!("{base}/unavailable"));
+        assert_eq!(fetch_github_membership(request, "organisation").await.unwrap_err().status(), StatusCode::ServiceUnavailable);
+    }) }
+
+    #[test]
+    fn lists_missing_scopes() {
+        assert!(missing_github_scopes("repo,read:org,read:user").is_empty());
+        assert_eq!(missing_github_scopes("read:user"), vec![ "read:org", "repo" ]);
+        assert_eq!(missing_github_scopes(""), vec![ "read:user", "read:org", "repo" ]);
+    }
+
+    #[cfg(feature = "account-db")]
+    #[test]
+    fn round_trips_through_record() {
+        let link = GithubLink {
+            user         : GithubUser { id : 1, login : "octocat".to_string() },
+            access_token : "token".to_string(),
+            scope        : "repo".to_string(),
+            maintainer   : GithubMembershipState::Pending
+        };
+        let restored = GithubLink::from(link.to_record());
+        assert_eq!((restored.user.id, restored.user.login.as_str(), restored.access_token.as_str()), (1, "octocat", "token"));
+        assert_eq!(restored.maintainer, GithubMembershipState::None);
+    }
+}
@@ -1,9 +1,18 @@
-use crate::util::{ image, math };
+use crate::{
+    cache::texture::TextureCache,
+    skin::{ CapeLayout, CapeTexture, SkinLayout, SkinModel, SkinTexture },
+    util::{ dotenv, ratelimit::UpstreamRateLimit }
+};
 use tide::StatusCode;
 use surf::Client;
 use serde::Deserialize as Deser;
+use surf::Body;
 use uuid::Uuid;
-use ::image::{ ImageBuffer, Rgba };
+use ::image::RgbaImage;
+use base64::{
+    prelude::BASE64_STANDARD,
+    Engine
+};
 
 
 pub async fn verify_account_product(
@@ -65,17 +74,29 @@ pub struct MinecraftAccountProfile {
     pub uuid     : Uuid,
     #[serde(rename = "name")]
     pub username : String,
-    pub skins    : Vec<MinecraftAccountSkin>
+    pub skins    : Vec<MinecraftAccountSkin>,
+    #[serde(default)]
+    pub capes    : Vec<MinecraftAccountCape>
 }
 
 #[derive(Deser, Debug)]
 pub struct MinecraftAccountSkin {
-    pub state   : MinecraftAccountSkinState,
-    pub url     : String
+    pub state   : MinecraftAccountTextureState,
+    pub url     : String,
+    #[serde(default)]
+    pub variant : SkinModel
+}
+
+#[derive(Deser, Debug)]
+pub struct MinecraftAccountCape {
+    pub id    : String,
+    pub state : MinecraftAccountTextureState,
+    pub url   : String,
+    pub alias : Option<String>
 }
 
 #[derive(Deser, Clone, Copy, PartialEq, Eq, Debug)]
-pub enum MinecraftAccountSkinState {
+pub enum MinecraftAccountTextureState {
     #[serde(rename = "ACTIVE")]
     Active,
     #[serde(rename = "INACTIVE")]
@@ -84,25 +105,205 @@ pub enum MinecraftAccountSkinState {
 
 impl MinecraftAccountProfile {
 
-    pub async fn get_active_skin(&self, client : &Client) -> surf::Result<Option<String>> {
-        let active_skin = self.skins.iter().find_map(|skin| (skin.state == MinecraftAccountSkinState::Active).then(|| &skin.url));
-        Ok(match (active_skin) {
-            Some(skin_url) => {
-                let     image_full = image::fetch(client, skin_url).await?.to_rgba32f();
-                let mut container  = [0u8; 8*8*4];
-                let mut image_face = ImageBuffer::<Rgba<u8>, _>::from_raw(8, 8, container.as_mut_slice()).unwrap();
-                for (x, y, px,) in image_face.enumerate_pixels_mut() {
-                    let head = image_full.get_pixel(8 + x, 8 + y);
-                    let cap  = image_full.get_pixel(40 + x, 8 + y);
-                    px.0[0] = (math::lerp(head.0[0]*head.0[3], cap.0[0], cap.0[3]).clamp(0.0, 1.0) * (u8::MAX as f32)) as u8;
-                    px.0[1] = (math::lerp(head.0[1]*head.0[3], cap.0[1], cap.0[3]).clamp(0.0, 1.0) * (u8::MAX as f32)) as u8;
-                    px.0[2] = (math::lerp(head.0[2]*head.0[3], cap.0[2], cap.0[3]).clamp(0.0, 1.0) * (u8::MAX as f32)) as u8;
-                    px.0[3] = u8::MAX;
-                }
-                Some(image::to_base64(&image_face)?)
-            },
-            None => None
+    pub fn active_skin(&self) -> Option<SkinTexture> {
+        self.skins.iter().find_map(|skin| (skin.state == MinecraftAccountTextureState::Active).then(|| SkinTexture {
+            url   : skin.url.clone(),
+            model : skin.variant
+        }))
+    }
+
+    pub async fn get_active_skin(&self, client : &Client, textures : &TextureCache) -> surf::Result<Option<RgbaImage>> {
+        Ok(match (self.active_skin()) {
+            Some(skin) => Some(fetch_skin(client, textures, &skin.url).await?),
+            None       => None
+        })
+    }
+
+    pub fn active_cape(&self) -> Option<CapeTexture> {
+        self.capes.iter().find_map(|cape| (cape.state == MinecraftAccountTextureState::Active).then(|| CapeTexture {
+            url   : cape.url.clone(),
+            alias : cape.alias.clone()
+        }))
+    }
+
+    pub async fn get_active_cape(&self, client : &Client, textures : &TextureCache) -> surf::Result<Option<RgbaImage>> {
+        Ok(match (self.active_cape()) {
+            Some(cape) => Some(fetch_cape(client, textures, &cape.url).await?),
+            None       => None
         })
     }
 
 }
+
+
+pub async fn fetch_skin(client : &Client, textures : &TextureCache, skin_url : &str) -> surf::Result<RgbaImage> {
+    let texture = textures.fetch(client, skin_url).await?.to_rgba8();
+    SkinLayout::from_dimensions(texture.width(), texture.height()).map_err(|err| {
+        surf::Error::from_str(StatusCode::UnprocessableEntity, format!("Failed to fetch Minecraft skin: {err}"))
+    })?;
+    Ok(texture)
+}
+
+pub async fn fetch_cape(client : &Client, textures : &TextureCache, cape_url : &str) -> surf::Result<RgbaImage> {
+    let texture = textures.fetch(client, cape_url).await?.to_rgba8();
+    CapeLayout::from_dimensions(texture.width(), texture.height()).map_err(|err| {
+        surf::Error::from_str(StatusCode::UnprocessableEntity, format!("Failed to fetch Minecraft cape: {err}"))
+    })?;
+    Ok(texture)
+}
+
+
+fn session_server_base() -> &'static str {
+    dotenv::try_var("MOJANG_SESSION_SERVER_BASE").unwrap_or("https://sessionserver.mojang.com")
+}
+
+pub async fn fetch_public_profile(
+    client         : &Client,
+    minecraft_uuid : Uuid,
+    upstream_limit : &mut UpstreamRateLimit
+) -> surf::Result<Option<MinecraftPublicProfile>> {
+    let request = client.get(format!("{}/session/minecraft/profile/{}", session_server_base(), minecraft_uuid.simple()));
+    let mut response = request.send().await.map_err(|err| {
+        surf::Error::from_str(err.status(), format!("Failed to fetch Minecraft public profile: {}", err.into_inner()))
+    })?;
+    *upstream_limit = UpstreamRateLimit::from_response(&response);
+    let status = response.status();
+    if (status == StatusCode::NoContent || status == StatusCode::NotFound) {
+        return Ok(None);
+    }
+    if (! status.is_success()) {
+        return Err(surf::Error::from_str(status, format!("Failed to fetch Minecraft public profile: {}", status.canonical_reason())));
+    }
+    let profile = response.body_json::<MinecraftPublicProfileDeser>().await?;
+    let mut skin = None;
+    let mut cape = None;
+    if let Some(textures) = profile.properties.iter().find(|p| p.name == "textures") {
+        let textures = BASE64_STANDARD.decode(&textures.value).map_err(|err| {
+            surf::Error::from_str(StatusCode::BadGateway, format!("Failed to decode Minecraft profile textures: {err}"))
+        })?;
+        let textures = serde_json::from_slice::<MinecraftProfileTexturesDeser>(&textures).map_err(|err| {
+            surf::Error::from_str(StatusCode::BadGateway, format!("Failed to decode Minecraft profile textures: {err}"))
+        })?;
+        skin = textures.textures.skin.map(|skin| SkinTexture {
+            url   : skin.url,
+            model : skin.metadata.map_or(SkinModel::Classic, |metadata| metadata.model)
+        });
+        cape = textures.textures.cape.map(|cape| CapeTexture {
+            url   : cape.url,
+            alias : None
+        });
+    }
+    Ok(Some(MinecraftPublicProfile {
+        uuid     : profile.uuid,
+        username : profile.username,
+        skin,
+        cape
+    }))
+}
+
+#[derive(Deser)]
+struct MinecraftPublicProfileDeser {
+    #[serde(rename = "id")]
+    uuid       : Uuid,
+    #[serde(rename = "name")]
+    username   : String,
+    #[serde(default)]
+    properties : Vec<MinecraftPublicProfileProperty>
+}
+#[derive(Deser)]
+struct MinecraftPublicProfileProperty {
+    name  : String,
+    value : String
+}
+#[derive(Deser)]
+struct MinecraftProfileTexturesDeser {
+    textures : MinecraftProfileTexturesInnerDeser
+}
+#[derive(Deser)]
+struct MinecraftProfileTexturesInnerDeser {
+    #[serde(rename = "SKIN")]
+    skin : Option<MinecraftProfileTextureDeser>,
+    #[serde(rename = "CAPE")]
+    cape : Option<MinecraftProfileTextureDeser>
+}
+#[derive(Deser)]
+struct MinecraftProfileTextureDeser {
+    url      : String,
+    metadata : Option<MinecraftProfileTextureMetadataDeser>
+}
+#[derive(Deser)]
+struct MinecraftProfileTextureMetadataDeser {
+    #[serde(default)]
+    model : SkinModel
+}
+
+#[derive(Debug)]
+pub struct MinecraftPublicProfile {
+    pub uuid     : Uuid,
+    pub username : String,
+    pub skin     : Option<SkinTexture>,
+    pub cape     : Option<CapeTexture>
+}
+
+
+pub fn is_valid_username(username : &str) -> bool {
+    (1..=16).contains(&username.len())
+        && username.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+
+fn mojang_api_base() -> &'static str {
+    dotenv::try_var("MOJANG_API_BASE").unwrap_or("https://api.mojang.com")
+}
+
+fn minecraft_services_base() -> &'static str {
+    dotenv::try_var("MINECRAFT_SERVICES_BASE").unwrap_or("https://api.minecraftservices.com")
+}
+
+pub async fn fetch_uuid_by_name(
+    client         : &Client,
+    username       : &str,
+    upstream_limit : &mut UpstreamRateLimit
+) -> surf::Result<Option<MinecraftProfileName>> {
+    let request = client.get(format!("{}/users/profiles/minecraft/{username}", mojang_api_base()));
+    let mut response = request.send().await.map_err(|err| {
+        surf::Error::from_str(err.status(), format!("Failed to resolve Minecraft username: {}", err.into_inner()))
+    })?;
+    *upstream_limit = UpstreamRateLimit::from_response(&response);
+    let status = response.status();
+    if (status == StatusCode::NoContent || status == StatusCode::NotFound) {
+        return Ok(None);
+    }
+    if (! status.is_success()) {
+        return Err(surf::Error::from_str(status, format!("Failed to resolve Minecraft username: {}", status.canonical_reason())));
+    }
+    Ok(Some(response.body_json::<MinecraftProfileName>().await?))
+}
+
+pub async fn fetch_uuids_by_names(
+    client         : &Client,
+    usernames      : &[&str],
+    upstream_limit : &mut UpstreamRateLimit
+) -> surf::Result<Vec<MinecraftProfileName>> {
+    let request = client.post(format!("{}/minecraft/profile/lookup/bulk/byname", minecraft_services_base()))
+        .header("Content-Type", "application/json")
+        .header("Accept", "application/json")
+        .body(Body::from_json(&usernames)?);
+    let mut response = request.send().await.map_err(|err| {
+        surf::Error::from_str(err.status(), format!("Failed to resolve Minecraft usernames: {}", err.into_inner()))
+    })?;
+    *upstream_limit = UpstreamRateLimit::from_response(&response);
+    let status = response.status();
+    if (! status.is_success()) {
+        return Err(surf::Error::from_str(status, format!("Failed to resolve Minecraft usernames: {}", status.canonical_reason())));
+    }
+    response.body_json::<Vec<MinecraftProfileName>>().await
+}
+
+#[derive(Deser, Debug)]
+pub struct MinecraftProfileName {
+    #[serde(rename = "id")]
+    pub uuid     : Uuid,
+    #[serde(rename = "name")]
+    pub username : String
+}
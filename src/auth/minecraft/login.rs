@@ -1,5 +1,7 @@
-use crate::util::dotenv;
-use core::fmt;
+use crate::{
+    i18n::{ Locale, t },
+    util::dotenv
+};
 use std::borrow::Cow;
 use surf::{ Client, Body };
 use urlencoding::encode as urlencode;
@@ -114,7 +116,7 @@ pub struct XboxAuth {
 }
 
 
-pub async fn exchange_xsts_token(client : &Client, xbox_token : &str) -> surf::Result<String> {
+pub async fn exchange_xsts_token(client : &Client, xbox_token : &str, locale : Locale) -> surf::Result<String> {
     let request = client.post("https://xsts.auth.xboxlive.com/xsts/authorize")
         .header("Content-Type", "application/json")
         .header("Accept", "application/json")
@@ -125,8 +127,8 @@ pub async fn exchange_xsts_token(client : &Client, xbox_token : &str) -> surf::R
     let status = response.status();
     if (! status.is_success()) {
         return Err(surf::Error::from_str(status, match (response.body_json::<XstsTokenError>().await) {
-            Ok(err) => format!("Failed to exchange XBOX auth token for XSTS Minecraft token: {}", XstsTokenErrorCode::from(err.code)),
-            Err(_)  => format!("Failed to exchange XBOX auth token for XSTS Minecraft token: {}", status.canonical_reason())
+            Ok(err) => t!(locale, "login-error-xsts", reason = XstsTokenErrorCode::from(err.code).message(locale)),
+            Err(_)  => t!(locale, "login-error-xsts", reason = status.canonical_reason())
         }));
     }
     Ok(response.body_json::<XstsTokenDeser>().await?.token)
@@ -166,15 +168,15 @@ impl From<usize> for XstsTokenErrorCode {
         }
     }
 }
-impl fmt::Display for XstsTokenErrorCode {
-    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+impl XstsTokenErrorCode {
+    fn message(&self, locale : Locale) -> String {
         match (self) {
-            XstsTokenErrorCode::Banned             => write!(f, "Account is banned from Xbox"),
-            XstsTokenErrorCode::NoXbox             => write!(f, "Account does not have an Xbox profile"),
-            XstsTokenErrorCode::UnavailableCountry => write!(f, "Xbox is unavailable in account country"),
-            XstsTokenErrorCode::AdultVerifRequired => write!(f, "Account needs adult verification on Xbox (South Korea)"),
-            XstsTokenErrorCode::Underage           => write!(f, "Account is underage and needs to be added to a Family."),
-            XstsTokenErrorCode::Unknown            => write!(f, "Unknown error")
+            XstsTokenErrorCode::Banned             => t!(locale, "login-xsts-banned"),
+            XstsTokenErrorCode::NoXbox             => t!(locale, "login-xsts-no-xbox"),
+            XstsTokenErrorCode::UnavailableCountry => t!(locale, "login-xsts-unavailable-country"),
+            XstsTokenErrorCode::AdultVerifRequired => t!(locale, "login-xsts-adult-verification"),
+            XstsTokenErrorCode::Underage           => t!(locale, "login-xsts-underage"),
+            XstsTokenErrorCode::Unknown            => t!(locale, "login-xsts-unknown")
         }
     }
 }
@@ -1,3 +1,5 @@
 pub mod login;
 
 pub mod account;
+
+pub mod provider;
@@ -0,0 +1,75 @@
+use crate::{
+    auth::{
+        minecraft::{ account, login },
+        provider::{ self, AuthIdentity, AuthProvider, AuthProviderKind }
+    },
+    i18n::Locale,
+    layout,
+    site::{ self, SharedSiteState }
+};
+use tide::{
+    Request,
+    Response,
+    utils::async_trait
+};
+use surf::Client;
+use serde::Deserialize as Deser;
+
+
+pub struct MicrosoftAuthProvider;
+
+#[derive(Deser)]
+struct MicrosoftOauthQuery {
+    #[serde(rename = "code")]
+    microsoft_code : String,
+    state          : String
+}
+
+#[async_trait]
+impl AuthProvider for MicrosoftAuthProvider {
+
+    fn id(&self) -> &'static str { "microsoft" }
+
+    fn name(&self, _locale : Locale) -> String { "Microsoft".to_string() }
+
+    fn kind(&self) -> AuthProviderKind { AuthProviderKind::Login }
+
+    fn render_button(&self, _locale : Locale) -> layout::PreEscaped<String> {
+        ::maud::html!{
+            a href=(format!("/auth/{}/start", self.id())) {
+                (layout::themed_icon_svg!("brand/microsoft_signin_dark.svg", "brand/microsoft_signin_light.svg"))
+            }
+        }
+    }
+
+    async fn start(&self, req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+        let state = provider::begin_oauth_state(req, self.id());
+        Ok(tide::Redirect::see_other(login::build_microsoft_access_code_url(Some(&state))).into())
+    }
+
+    async fn callback(&self, req : &mut Request<SharedSiteState>) -> tide::Result<AuthIdentity> {
+        let query = req.query::<MicrosoftOauthQuery>()?;
+        provider::take_oauth_state(req, self.id(), &query.state)?;
+
+        let client = Client::new();
+        let microsoft_token   = login::exchange_microsoft_token(&client, &query.microsoft_code).await?;
+        let xbox_auth         = login::exchange_xbox_auth(&client, &microsoft_token.access_token).await?;
+        let xsts_token        = login::exchange_xsts_token(&client, &xbox_auth.token, site::locale(req)).await?;
+        let minecraft_token   = login::exchange_minecraft_token(&client, &xbox_auth.userhash, &xsts_token).await?;
+                                // account::verify_account_product(&client, &minecraft_token).await?;
+        let minecraft_profile = account::fetch_account_profile(&client, &minecraft_token).await?;
+        let textures          = req.state().textures();
+        let minecraft_skin    = minecraft_profile.get_active_skin(&client, textures).await?;
+        // Only the face is kept in memory; fetching the cape here warms the texture cache, and `/cape` retries if it fails.
+        _ = minecraft_profile.get_active_cape(&client, textures).await;
+
+        Ok(AuthIdentity::Minecraft {
+            uuid     : minecraft_profile.uuid,
+            username : minecraft_profile.username.clone(),
+            skin     : minecraft_profile.active_skin(),
+            texture  : minecraft_skin,
+            cape     : minecraft_profile.active_cape()
+        })
+    }
+
+}
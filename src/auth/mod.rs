@@ -1,3 +1,8 @@
+pub mod provider;
+
 pub mod minecraft;
 
 pub mod github;
+
+#[cfg(feature = "dev-login")]
+pub mod dev;
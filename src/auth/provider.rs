@@ -0,0 +1,83 @@
+use crate::{
+    auth::github::GithubLink,
+    i18n::Locale,
+    layout::{ self, Render },
+    skin::{ CapeTexture, SkinTexture },
+    site::SharedSiteState,
+    util::rand
+};
+use tide::{
+    Request,
+    Response,
+    StatusCode,
+    utils::async_trait
+};
+use uuid::Uuid;
+use image::RgbaImage;
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AuthProviderKind {
+    Login,
+    Link
+}
+
+
+pub enum AuthIdentity {
+    Minecraft {
+        uuid     : Uuid,
+        username : String,
+        skin     : Option<SkinTexture>,
+        texture  : Option<RgbaImage>,
+        cape     : Option<CapeTexture>
+    },
+    Github(GithubLink)
+}
+
+
+#[async_trait]
+pub trait AuthProvider : Send + Sync {
+
+    fn id(&self) -> &'static str;
+
+    fn name(&self, locale : Locale) -> String;
+
+    fn kind(&self) -> AuthProviderKind;
+
+    fn render_button(&self, locale : Locale) -> layout::PreEscaped<String> {
+        layout::component::IconLink {
+            href     : &format!("/auth/{}/start", self.id()),
+            icon     : self.icon(),
+            label    : self.name(locale),
+            external : false
+        }.render()
+    }
+
+    fn icon(&self) -> layout::PreEscaped<String> {
+        layout::icon_svg!("account.svg")
+    }
+
+    async fn start(&self, req : &mut Request<SharedSiteState>) -> tide::Result<Response>;
+
+    async fn callback(&self, req : &mut Request<SharedSiteState>) -> tide::Result<AuthIdentity>;
+
+}
+
+
+pub fn begin_oauth_state(req : &mut Request<SharedSiteState>, provider : &str) -> String {
+    let state = rand::gen_token();
+    req.session_mut().insert_raw(&format!("{provider}-oauth-state"), state.clone());
+    state
+}
+
+pub fn take_oauth_state(req : &mut Request<SharedSiteState>, provider : &str, state : &str) -> tide::Result<()> {
+    let key      = format!("{provider}-oauth-state");
+    let session  = req.session_mut();
+    let expected = session.get_raw(&key);
+    session.remove(&key);
+    match (expected) {
+        Some(expected) if (rand::constant_time_eq(&expected, state)) => Ok(()),
+        Some(_) => Err(tide::Error::from_str(StatusCode::BadRequest, "Failed to authenticate: State mismatch")),
+        None    => Err(tide::Error::from_str(StatusCode::BadRequest, "Failed to authenticate: No authentication in progress"))
+    }
+}
@@ -0,0 +1,4 @@
+pub mod profile;
+pub mod render;
+pub mod status;
+pub mod texture;
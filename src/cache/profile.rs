@@ -0,0 +1,541 @@
+use crate::{
+    auth::minecraft::account,
+    cache::texture::TextureCache,
+    skin::{ self, CapeTexture, SkinTexture },
+    util::ratelimit::{ RateBudget, UpstreamRateLimit }
+};
+use core::time::Duration;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{ AtomicU64, Ordering }
+    },
+    time::Instant
+};
+use tide::StatusCode;
+use surf::Client;
+use smol::{
+    Timer,
+    lock::{ Mutex, RwLock }
+};
+use uuid::Uuid;
+use image::{
+    RgbaImage,
+    imageops::{ self, FilterType }
+};
+use chrono::{ DateTime, TimeDelta, Utc };
+
+
+const PROFILE_TTL      : TimeDelta = TimeDelta::minutes(30);
+const REFRESH_INTERVAL : Duration  = Duration::from_mins(1);
+const BUDGET_CAPACITY  : u64       = 100;
+const BUDGET_WINDOW    : Duration  = Duration::from_mins(1);
+// Image routes take anonymous requests for any UUID, so they only get a share of the budget.
+const PUBLIC_CAPACITY  : u64       = 20;
+// Anyone can ask for any profile, so the cache is bounded: idle profiles are dropped, and past the
+//  capacity the least recently used go first.
+const PROFILE_CAPACITY : usize     = 10_000;
+const PROFILE_IDLE_TTL : Duration  = Duration::from_hours(1);
+// Only logged-in users seen this recently have their profile refreshed in the background.
+const ACTIVE_WINDOW    : Duration  = Duration::from_mins(30);
+
+pub const BULK_LOOKUP_MAX : usize = 10;
+
+
+// Full skin and cape textures are left to `TextureCache`, which keeps them on disk.
+#[derive(Debug)]
+pub struct CachedProfile {
+    pub uuid       : Uuid,
+    pub username   : String,
+    pub skin       : Option<SkinTexture>,
+    pub face       : Option<Arc<RgbaImage>>,
+    pub cape       : Option<CapeTexture>,
+    pub fetched_at : Option<DateTime<Utc>>
+}
+
+impl CachedProfile {
+
+    pub fn is_stale(&self) -> bool {
+        self.fetched_at.is_none_or(|fetched_at| (Utc::now() - fetched_at) >= PROFILE_TTL)
+    }
+
+    // HD skins have larger faces, but avatars are scaled from 8x8 so every size is a whole multiple.
+    pub fn face_of(texture : Option<&RgbaImage>) -> Option<Arc<RgbaImage>> {
+        let face = skin::face(texture?).ok()?;
+        Some(Arc::new(if (face.width() == 8) { face } else { imageops::resize(&face, 8, 8, FilterType::Nearest) }))
+    }
+
+    pub fn max_age(&self) -> Duration {
+        self.fetched_at.map_or(Duration::ZERO, |fetched_at| (PROFILE_TTL - (Utc::now() - fetched_at)).to_std().unwrap_or(Duration::ZERO))
+    }
+
+}
+
+
+struct ProfileEntry {
+    profile   : Arc<CachedProfile>,
+    // Seconds since `ProfileCache::started`, so lookups only need the read lock to touch it.
+    last_used : AtomicU64
+}
+
+
+pub struct ProfileCache {
+    profiles : RwLock<HashMap<Uuid, ProfileEntry>>,
+    by_name  : RwLock<HashMap<String, Uuid>>,
+    active   : Mutex<HashMap<Uuid, Instant>>,
+    budget   : Mutex<RateBudget>,
+    public   : Mutex<RateBudget>,
+    textures : Arc<TextureCache>,
+    started  : Instant
+}
+
+impl ProfileCache {
+
+    pub fn new(textures : Arc<TextureCache>) -> Self {
+        Self {
+            profiles : RwLock::new(HashMap::new()),
+            by_name  : RwLock::new(HashMap::new()),
+            active   : Mutex::new(HashMap::new()),
+            budget   : Mutex::new(RateBudget::new(BUDGET_CAPACITY, BUDGET_WINDOW)),
+            public   : Mutex::new(RateBudget::new(PUBLIC_CAPACITY, BUDGET_WINDOW)),
+            textures,
+            started  : Instant::now()
+        }
+    }
+
+    fn now(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    pub async fn lookup(&self, uuid : Uuid) -> Option<Arc<CachedProfile>> {
+        let profiles = self.profiles.read().await;
+        let entry    = profiles.get(&uuid)?;
+        entry.last_used.store(self.now(), Ordering::Relaxed);
+        Some(Arc::clone(&entry.profile))
+    }
+
+    pub async fn lookup_by_name(&self, username : &str) -> Option<Arc<CachedProfile>> {
+        let uuid = *self.by_name.read().await.get(&username.to_ascii_lowercase())?;
+        self.lookup(uuid).await.filter(|profile| profile.username.eq_ignore_ascii_case(username))
+    }
+
+    pub async fn insert(&self, profile : CachedProfile) -> Arc<CachedProfile> {
+        let profile = Arc::new(profile);
+        self.by_name.write().await.insert(profile.username.to_ascii_lowercase(), profile.uuid);
+        let evicted = {
+            let mut profiles = self.profiles.write().await;
+            profiles.insert(profile.uuid, ProfileEntry { profile : Arc::clone(&profile), last_used : AtomicU64::new(self.now()) });
+            if (profiles.len() > PROFILE_CAPACITY) {
+                // Evicting a tenth at a time keeps the sort off the path of every insert.
+                let mut by_age = profiles.iter().map(|(uuid, entry)| (entry.last_used.load(Ordering::Relaxed), *uuid)).collect::<Vec<_>>();
+                by_age.sort_unstable();
+                by_age.into_iter().take(PROFILE_CAPACITY / 10).filter_map(|(_, uuid)| profiles.remove(&uuid)).map(|entry| entry.profile).collect()
+            } else { Vec::new() }
+        };
+        self.forget_names(&evicted).await;
+        profile
+    }
+
+    async fn remove(&self, uuid : Uuid) {
+        let removed = self.profiles.write().await.remove(&uuid);
+        if let Some(entry) = removed {
+            self.forget_names(&[ entry.profile ]).await;
+        }
+    }
+
+    async fn forget_names(&self, profiles : &[Arc<CachedProfile>]) {
+        if (profiles.is_empty()) { return; }
+        let mut by_name = self.by_name.write().await;
+        for profile in profiles {
+            let name = profile.username.to_ascii_lowercase();
+            if (by_name.get(&name) == Some(&profile.uuid)) {
+                by_name.remove(&name);
+            }
+        }
+    }
+
+    pub async fn evict_idle(&self) {
+        let cutoff  = self.now().saturating_sub(PROFILE_IDLE_TTL.as_secs());
+        let evicted = {
+            let mut profiles = self.profiles.write().await;
+            let     idle     = profiles.iter().filter(|(_, entry)| entry.last_used.load(Ordering::Relaxed) < cutoff).map(|(uuid, _)| *uuid).collect::<Vec<_>>();
+            idle.into_iter().filter_map(|uuid| profiles.remove(&uuid)).map(|entry| entry.profile).collect::<Vec<_>>()
+        };
+        self.forget_names(&evicted).await;
+    }
+
+    // Called for every request with a valid login session.
+    pub async fn mark_active(&self, uuid : Uuid) {
+        self.active.lock().await.insert(uuid, Instant::now());
+    }
+
+    pub async fn skin_texture(&self, client : &Client, profile : &CachedProfile) -> surf::Result<Option<RgbaImage>> {
+        Ok(match (&profile.skin) {
+            Some(skin) => Some(account::fetch_skin(client, &self.textures, &skin.url).await?),
+            None       => None
+        })
+    }
+
+    pub async fn cape_texture(&self, client : &Client, profile : &CachedProfile) -> surf::Result<Option<RgbaImage>> {
+        Ok(match (&profile.cape) {
+            Some(cape) => Some(account::fetch_cape(client, &self.textures, &cape.url).await?),
+            None       => None
+        })
+    }
+
+    async fn acquire_budget(&self, units : u64) -> surf::Result<()> {
+        if (self.budget.lock().await.try_acquire(units)) { Ok(()) }
+        else { Err(surf::Error::from_str(StatusCode::TooManyRequests, "Minecraft profile lookup rate limit budget exhausted")) }
+    }
+
+    pub async fn retry_after(&self) -> Duration {
+        self.budget.lock().await.retry_after()
+    }
+
+    pub async fn seed(&self, uuid : Uuid, username : &str) {
+        if (self.lookup(uuid).await.is_none()) {
+            self.insert(CachedProfile {
+                uuid,
+                username   : username.to_string(),
+                skin       : None,
+                face       : None,
+                cape       : None,
+                fetched_at : None
+            }).await;
+        }
+    }
+
+    pub async fn refresh(&self, client : &Client, uuid : Uuid) -> surf::Result<Option<Arc<CachedProfile>>> {
+        self.acquire_budget(1).await?;
+        self.fetch(client, uuid).await
+    }
+
+    // Callers must have taken a budget unit for the request.
+    async fn fetch(&self, client : &Client, uuid : Uuid) -> surf::Result<Option<Arc<CachedProfile>>> {
+        let mut upstream_limit = UpstreamRateLimit::default();
+        let     fetched        = account::fetch_public_profile(client, uuid, &mut upstream_limit).await;
+        self.budget.lock().await.record(&upstream_limit);
+
+        let Some(fetched) = fetched? else {
+            self.remove(uuid).await;
+            return Ok(None);
+        };
+        let previous = self.lookup(uuid).await;
+        let face     = match (&previous) {
+            Some(previous) if (previous.face.is_some() && previous.skin == fetched.skin) => previous.face.clone(),
+            _ => match (&fetched.skin) {
+                Some(skin) => CachedProfile::face_of(Some(&account::fetch_skin(client, &self.textures, &skin.url).await?)),
+                None       => None
+            }
+        };
+        // The public profile carries no cape alias, so keep the one seen at login while the cape is unchanged.
+        let cape = match (&previous, fetched.cape) {
+            (Some(previous), Some(cape)) if (previous.cape.as_ref().is_some_and(|previous| previous.url == cape.url)) => previous.cape.clone(),
+            (_, cape) => cape
+        };
+        Ok(Some(self.insert(CachedProfile {
+            uuid       : fetched.uuid,
+            username   : fetched.username,
+            skin       : fetched.skin,
+            face,
+            cape,
+            fetched_at : Some(Utc::now())
+        }).await))
+    }
+
+    pub async fn resolve(&self, client : &Client, uuid : Uuid) -> surf::Result<Option<Arc<CachedProfile>>> {
+        self.resolve_with(client, uuid, true).await
+    }
+
+    // Uncharged resolves are covered by a unit the caller already took.
+    async fn resolve_with(&self, client : &Client, uuid : Uuid, charge : bool) -> surf::Result<Option<Arc<CachedProfile>>> {
+        let cached = self.lookup(uuid).await;
+        if let Some(cached) = &cached && (! cached.is_stale()) {
+            return Ok(Some(Arc::clone(cached)));
+        }
+        let refreshed = if (charge) { self.refresh(client, uuid).await } else { self.fetch(client, uuid).await };
+        match (refreshed) {
+            Err(err) if (err.status() == StatusCode::TooManyRequests && cached.is_some()) => Ok(cached),
+            result => result
+        }
+    }
+
+    // Falls back to whatever is cached once the public share is used up or the lookup fails.
+    pub async fn resolve_public(&self, client : &Client, uuid : Uuid) -> Option<Arc<CachedProfile>> {
+        let cached = self.lookup(uuid).await;
+        if (cached.as_ref().is_some_and(|cached| ! cached.is_stale()) || ! self.public.lock().await.try_acquire(1)) {
+            return cached;
+        }
+        self.resolve(client, uuid).await.unwrap_or(cached)
+    }
+
+    // The profiles resolved before an error are returned along with it.
+    pub async fn resolve_many(&self, client : &Client, uuids : &[Uuid]) -> (Vec<Arc<CachedProfile>>, surf::Result<()>) {
+        let mut resolved = Vec::with_capacity(uuids.len());
+        for &uuid in uuids {
+            match (self.resolve(client, uuid).await) {
+                Ok(profile) => resolved.extend(profile),
+                Err(err)    => return (resolved, Err(err))
+            }
+        }
+        (resolved, Ok(()))
+    }
+
+    // One unit covers the name lookup and one more the profile it points to.
+    pub async fn resolve_by_name(&self, client : &Client, username : &str) -> surf::Result<Option<Arc<CachedProfile>>> {
+        if let Some(cached) = self.lookup_by_name(username).await && (! cached.is_stale()) {
+            return Ok(Some(cached));
+        }
+        self.acquire_budget(2).await?;
+        let mut upstream_limit = UpstreamRateLimit::default();
+        let     fetched        = account::fetch_uuid_by_name(client, username, &mut upstream_limit).await;
+        self.budget.lock().await.record(&upstream_limit);
+        match (fetched?) {
+            Some(fetched) => self.resolve_with(client, fetched.uuid, false).await,
+            None          => Ok(None)
+        }
+    }
+
+    // Each chunk takes its whole budget up front, so it is never cut off by the budget halfway through.
+    //  The profiles resolved before an error are returned along with it.
+    pub async fn resolve_by_names(&self, client : &Client, usernames : &[&str]) -> (Vec<Arc<CachedProfile>>, surf::Result<()>) {
+        let mut resolved = Vec::with_capacity(usernames.len());
+        let mut missing  = Vec::new();
+        for &username in usernames {
+            match (self.lookup_by_name(username).await) {
+                Some(cached) if (! cached.is_stale()) => resolved.push(cached),
+                _                                     => missing.push(username)
+            }
+        }
+        for chunk in missing.chunks(BULK_LOOKUP_MAX) {
+            if let Err(err) = self.acquire_budget(1 + chunk.len() as u64).await {
+                return (resolved, Err(err));
+            }
+            let mut upstream_limit = UpstreamRateLimit::default();
+            let     fetched        = account::fetch_uuids_by_names(client, chunk, &mut upstream_limit).await;
+            self.budget.lock().await.record(&upstream_limit);
+            let fetched = match (fetched) {
+                Ok(fetched) => fetched,
+                Err(err)    => return (resolved, Err(err))
+            };
+            for fetched in fetched {
+                match (self.resolve_with(client, fetched.uuid, false).await) {
+                    Ok(profile) => resolved.extend(profile),
+                    Err(err)    => return (resolved, Err(err))
+                }
+            }
+        }
+        (resolved, Ok(()))
+    }
+
+    pub async fn refresh_stale(&self, client : &Client) {
+        let active = {
+            let mut active = self.active.lock().await;
+            active.retain(|_, seen| seen.elapsed() < ACTIVE_WINDOW);
+            active.keys().copied().collect::<Vec<_>>()
+        };
+        let mut stale = {
+            let profiles = self.profiles.read().await;
+            active.into_iter()
+                .filter_map(|uuid| profiles.get(&uuid))
+                .filter(|entry| entry.profile.is_stale())
+                .map(|entry| (entry.profile.fetched_at, entry.profile.uuid))
+                .collect::<Vec<_>>()
+        };
+        stale.sort();
+        for (_, uuid) in stale {
+            if (self.budget.lock().await.remaining() == 0) { break; }
+            if let Err(err) = self.refresh(client, uuid).await && err.status() == StatusCode::TooManyRequests {
+                break;
+            }
+        }
+    }
+
+    pub fn spawn_refresher(self : &Arc<Self>) {
+        let cache = Arc::clone(self);
+        smol::spawn(async move {
+            let client = Client::new();
+            loop {
+                Timer::after(REFRESH_INTERVAL).await;
+                cache.evict_idle().await;
+                cache.refresh_stale(&client).await;
+            }
+        }).detach();
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::dotenv;
+    use std::sync::atomic::AtomicUsize;
+    use tide::{ Body, Response };
+
+    #[derive(Clone, Default)]
+    struct MockHits {
+        profiles : Arc<AtomicUsize>,
+        names    : Arc<AtomicUsize>
+    }
+
+    // Usernames map to the UUID of their length, so `Steve` is `…05`.
+    fn mock_uuid(username : &str) -> Uuid {
+        Uuid::from_u128(if (username == "Limited") { u128::MAX } else { username.len() as u128 })
+    }
+
+    // A stand-in for the Mojang APIs. `Limited`'s profile is rate limited.
+    async fn mock_mojang() -> MockHits {
+        let hits    = MockHits::default();
+        let mut app = tide::with_state(hits.clone());
+        app.at("/session/minecraft/profile/:uuid").get(|req : tide::Request<MockHits>| async move {
+            req.state().profiles.fetch_add(1, Ordering::Relaxed);
+            let uuid = req.param("uuid")?;
+            if (uuid.starts_with('f')) {
+                let mut res = Response::new(StatusCode::TooManyRequests);
+                res.insert_header("Retry-After", "30");
+                return Ok(res);
+            }
+            Ok(Body::from_json(&serde_json::json!({ "id" : uuid, "name" : "Steve", "properties" : [] }))?.into())
+        });
+        app.at("/users/profiles/minecraft/:name").get(|req : tide::Request<MockHits>| async move {
+            req.state().names.fetch_add(1, Ordering::Relaxed);
+            let name = req.param("name")?;
+            Ok(Response::from(Body::from_json(&serde_json::json!({ "id" : mock_uuid(name), "name" : name }))?))
+        });
+        app.at("/minecraft/profile/lookup/bulk/byname").post(|mut req : tide::Request<MockHits>| async move {
+            req.state().names.fetch_add(1, Ordering::Relaxed);
+            let names = req.body_json::<Vec<String>>().await?;
+            Ok(Response::from(Body::from_json(&names.iter().map(|name| serde_json::json!({ "id" : mock_uuid(name), "name" : name })).collect::<Vec<_>>())?))
+        });
+
+        let mut listener = tide::listener::ToListener::<MockHits>::to_listener("127.0.0.1:0").unwrap();
+        tide::listener::Listener::bind(&mut listener, app).await.unwrap();
+        let base = tide::listener::Listener::info(&listener)[0].connection().to_string();
+        smol::spawn(async move { _ = tide::listener::Listener::accept(&mut listener).await; }).detach();
+
+        dotenv::set_for_test("MOJANG_SESSION_SERVER_BASE", &base);
+        dotenv::set_for_test("MOJANG_API_BASE", &base);
+        dotenv::set_for_test("MINECRAFT_SERVICES_BASE", &base);
+        hits
+    }
+
+    fn profile_cache() -> ProfileCache {
+        let dir = std::env::temp_dir().join(format!("profile-cache-{:016x}", rand::random::<u64>()));
+        ProfileCache::new(Arc::new(TextureCache::new(dir, 1 << 20)))
+    }
+
+    #[test]
+    fn refreshes_stale_active_profiles() { smol::block_on(async {
+        let hits   = mock_mojang().await;
+        let cache  = profile_cache();
+        let client = Client::new();
+        let active = Uuid::from_u128(1);
+        let idle   = Uuid::from_u128(2);
+        cache.seed(active, "Alex").await;
+        cache.seed(idle, "Alex").await;
+        cache.mark_active(active).await;
+
+        cache.refresh_stale(&client).await;
+        let refreshed = cache.lookup(active).await.unwrap();
+        assert_eq!(refreshed.username, "Steve");
+        assert!(! refreshed.is_stale());
+        assert!(cache.lookup(idle).await.unwrap().is_stale());
+        assert_eq!(hits.profiles.load(Ordering::Relaxed), 1);
+
+        // Fresh profiles are left alone.
+        cache.refresh_stale(&client).await;
+        assert_eq!(hits.profiles.load(Ordering::Relaxed), 1);
+    }) }
+
+    #[test]
+    fn serves_cached_profiles_while_rate_limited() { smol::block_on(async {
+        mock_mojang().await;
+        let cache  = profile_cache();
+        let client = Client::new();
+        let uuid   = Uuid::from_u128(u128::MAX);
+        cache.seed(uuid, "Alex").await;
+
+        let profile = cache.resolve(&client, uuid).await.unwrap().unwrap();
+        assert_eq!(profile.username, "Alex");
+        // Upstream's Retry-After holds back every later lookup.
+        assert!(cache.retry_after().await > Duration::from_secs(29));
+        let err = cache.resolve(&client, Uuid::from_u128(1)).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::TooManyRequests);
+    }) }
+
+    #[test]
+    fn stops_when_the_budget_runs_out() { smol::block_on(async {
+        let hits   = mock_mojang().await;
+        let cache  = profile_cache();
+        let client = Client::new();
+        assert!(cache.budget.lock().await.try_acquire(BUDGET_CAPACITY - 1));
+
+        let uuids = [ Uuid::from_u128(1), Uuid::from_u128(2) ];
+        let (resolved, result) = cache.resolve_many(&client, &uuids).await;
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(result.unwrap_err().status(), StatusCode::TooManyRequests);
+        assert_eq!(hits.profiles.load(Ordering::Relaxed), 1);
+    }) }
+
+    #[test]
+    fn caches_names() { smol::block_on(async {
+        let hits   = mock_mojang().await;
+        let cache  = profile_cache();
+        let client = Client::new();
+
+        let profile = cache.resolve_by_name(&client, "Steve").await.unwrap().unwrap();
+        assert_eq!(profile.uuid, mock_uuid("Steve"));
+        assert_eq!(cache.resolve_by_name(&client, "steve").await.unwrap().unwrap().uuid, profile.uuid);
+        assert_eq!((hits.names.load(Ordering::Relaxed), hits.profiles.load(Ordering::Relaxed)), (1, 1));
+        assert_eq!(cache.budget.lock().await.remaining(), BUDGET_CAPACITY - 2);
+    }) }
+
+    #[test]
+    fn charges_bulk_lookups_once_per_chunk() { smol::block_on(async {
+        let hits   = mock_mojang().await;
+        let cache  = profile_cache();
+        let client = Client::new();
+        cache.resolve_by_name(&client, "Steve").await.unwrap();
+
+        let (resolved, result) = cache.resolve_by_names(&client, &[ "Steve", "Alex", "Herobrine" ]).await;
+        result.unwrap();
+        assert_eq!(resolved.iter().map(|profile| profile.uuid).collect::<Vec<_>>(), [ mock_uuid("Steve"), mock_uuid("Alex"), mock_uuid("Herobrine") ]);
+        // The cached name needs no lookup, and the other two share one bulk request.
+        assert_eq!((hits.names.load(Ordering::Relaxed), hits.profiles.load(Ordering::Relaxed)), (2, 3));
+        assert_eq!(cache.budget.lock().await.remaining(), BUDGET_CAPACITY - 2 - 3);
+    }) }
+
+    #[test]
+    fn keeps_names_resolved_before_a_429() { smol::block_on(async {
+        mock_mojang().await;
+        let cache  = profile_cache();
+        let client = Client::new();
+
+        let (resolved, result) = cache.resolve_by_names(&client, &[ "Alex", "Limited", "Herobrine" ]).await;
+        assert_eq!(resolved.iter().map(|profile| profile.uuid).collect::<Vec<_>>(), [ mock_uuid("Alex") ]);
+        assert_eq!(result.unwrap_err().status(), StatusCode::TooManyRequests);
+    }) }
+
+    #[test]
+    fn limits_public_lookups() { smol::block_on(async {
+        let hits   = mock_mojang().await;
+        let cache  = profile_cache();
+        let client = Client::new();
+        for i in 0..PUBLIC_CAPACITY {
+            assert!(cache.resolve_public(&client, Uuid::from_u128(i as u128 + 1)).await.is_some());
+        }
+        // Past the public share only the cache answers, and the rest of the budget is left for everything else.
+        assert!(cache.resolve_public(&client, Uuid::from_u128(1)).await.is_some());
+        assert!(cache.resolve_public(&client, Uuid::from_u128(u128::MAX - 1)).await.is_none());
+        assert_eq!(hits.profiles.load(Ordering::Relaxed), PUBLIC_CAPACITY as usize);
+        assert_eq!(cache.budget.lock().await.remaining(), BUDGET_CAPACITY - PUBLIC_CAPACITY);
+    }) }
+
+    #[test]
+    fn scales_hd_faces_down() {
+        assert_eq!(CachedProfile::face_of(Some(&RgbaImage::new(64, 64))).unwrap().dimensions(), (8, 8));
+        assert_eq!(CachedProfile::face_of(Some(&RgbaImage::new(256, 256))).unwrap().dimensions(), (8, 8));
+    }
+}
@@ -0,0 +1,68 @@
+use crate::skin::render::RenderKind;
+use std::{
+    collections::{ HashMap, VecDeque },
+    sync::Arc
+};
+use smol::lock::Mutex;
+
+
+const RENDER_CACHE_CAPACITY : usize = 1024;
+
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct RenderKey {
+    pub texture_id : String,
+    pub cape_id    : Option<String>,
+    pub kind       : RenderKind,
+    pub size       : u32,
+    pub yaw        : i32,
+    pub pitch      : i32,
+    pub overlay    : bool
+}
+
+impl RenderKey {
+
+    pub fn etag(&self) -> String {
+        let kind = match (self.kind) {
+            RenderKind::Head => "head",
+            RenderKind::Body => "body"
+        };
+        let cape_id = self.cape_id.as_deref().unwrap_or("none");
+        format!("\"{}-{cape_id}-{kind}-{}-{}-{}-{}\"", self.texture_id, self.size, self.yaw, self.pitch, self.overlay as u8)
+    }
+
+}
+
+
+pub struct RenderCache {
+    renders : Mutex<(HashMap<RenderKey, Arc<Vec<u8>>>, VecDeque<RenderKey>)>
+}
+
+impl RenderCache {
+
+    pub fn new() -> Self {
+        Self {
+            renders : Mutex::new((HashMap::new(), VecDeque::new()))
+        }
+    }
+
+    pub async fn lookup(&self, key : &RenderKey) -> Option<Arc<Vec<u8>>> {
+        self.renders.lock().await.0.get(key).map(Arc::clone)
+    }
+
+    pub async fn insert(&self, key : RenderKey, png : Vec<u8>) -> Arc<Vec<u8>> {
+        let     png                = Arc::new(png);
+        let mut renders            = self.renders.lock().await;
+        let     (renders, order)   = &mut *renders;
+        if (renders.insert(key.clone(), Arc::clone(&png)).is_none()) {
+            order.push_back(key);
+            while (order.len() > RENDER_CACHE_CAPACITY) {
+                if let Some(evicted) = order.pop_front() {
+                    renders.remove(&evicted);
+                }
+            }
+        }
+        png
+    }
+
+}
@@ -0,0 +1,83 @@
+use crate::{
+    ping::{ self, ServerAddress, ServerStatus },
+    util::dotenv
+};
+use core::time::Duration;
+use std::sync::Arc;
+use smol::{
+    Timer,
+    lock::RwLock
+};
+use chrono::{ DateTime, Utc };
+
+
+const POLL_INTERVAL : Duration = Duration::from_secs(30);
+const PING_TIMEOUT  : Duration = Duration::from_secs(5);
+
+
+#[derive(Debug)]
+pub struct CachedStatus {
+    pub status     : Result<ServerStatus, String>,
+    pub checked_at : DateTime<Utc>,
+    // Kept through outages so the page can say how long the server has been down.
+    pub last_seen  : Option<DateTime<Utc>>
+}
+
+impl CachedStatus {
+
+    pub fn online(&self) -> Option<&ServerStatus> {
+        self.status.as_ref().ok()
+    }
+
+    pub fn max_age(&self) -> Duration {
+        (POLL_INTERVAL.saturating_sub((Utc::now() - self.checked_at).to_std().unwrap_or(Duration::ZERO))).max(Duration::from_secs(1))
+    }
+
+}
+
+
+pub struct StatusCache {
+    address : Option<ServerAddress>,
+    current : RwLock<Option<Arc<CachedStatus>>>
+}
+
+impl StatusCache {
+
+    pub fn from_env() -> Self {
+        Self {
+            address : dotenv::try_var("CENTRAL_SERVER_ADDRESS").and_then(ServerAddress::parse),
+            current : RwLock::new(None)
+        }
+    }
+
+    pub fn address(&self) -> Option<&ServerAddress> {
+        self.address.as_ref()
+    }
+
+    pub async fn current(&self) -> Option<Arc<CachedStatus>> {
+        self.current.read().await.as_ref().map(Arc::clone)
+    }
+
+    pub async fn refresh(&self) -> Option<Arc<CachedStatus>> {
+        let address    = self.address.as_ref()?;
+        let status     = ping::ping(address, PING_TIMEOUT).await.map_err(|err| format!("Failed to ping {address}: {err}"));
+        let checked_at = Utc::now();
+        let last_seen  = if (status.is_ok()) { Some(checked_at) }
+            else { self.current().await.and_then(|previous| previous.last_seen) };
+        let cached = Arc::new(CachedStatus { status, checked_at, last_seen });
+        *self.current.write().await = Some(Arc::clone(&cached));
+        Some(cached)
+    }
+
+    pub fn spawn_poller(self : &Arc<Self>) {
+        if (self.address.is_none()) { return; }
+        let cache = Arc::clone(self);
+        smol::spawn(async move {
+            loop {
+                cache.refresh().await;
+                Timer::after(POLL_INTERVAL).await;
+            }
+        }).detach();
+    }
+
+}
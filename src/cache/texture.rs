@@ -0,0 +1,249 @@
+use crate::{
+    skin,
+    util::{ dotenv, image }
+};
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::SystemTime
+};
+use surf::Client;
+use smol::lock::Mutex;
+use sha2::{ Digest, Sha256 };
+use ::image::DynamicImage;
+
+
+const DEFAULT_CACHE_DIR       : &str = "cache/textures";
+const DEFAULT_CACHE_MAX_BYTES : u64  = 256 * 1024 * 1024;
+
+
+struct TextureEntry {
+    bytes     : u64,
+    // SHA-256 of the stored file, also kept in its name so it survives restarts.
+    digest    : String,
+    last_used : u64
+}
+
+struct TextureIndex {
+    entries     : HashMap<String, TextureEntry>,
+    total_bytes : u64,
+    tick        : u64
+}
+
+impl TextureIndex {
+
+    fn touch(&mut self, hash : &str) {
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(hash) {
+            entry.last_used = self.tick;
+        }
+    }
+
+    fn insert(&mut self, hash : String, digest : String, bytes : u64) -> Option<TextureEntry> {
+        self.tick += 1;
+        let previous = self.entries.insert(hash, TextureEntry { bytes, digest, last_used : self.tick });
+        if let Some(previous) = &previous {
+            self.total_bytes -= previous.bytes;
+        }
+        self.total_bytes += bytes;
+        previous
+    }
+
+    fn remove(&mut self, hash : &str) -> Option<TextureEntry> {
+        let entry = self.entries.remove(hash)?;
+        self.total_bytes -= entry.bytes;
+        Some(entry)
+    }
+
+    fn least_recently_used(&self) -> Option<String> {
+        self.entries.iter().min_by_key(|(_, entry)| entry.last_used).map(|(hash, _)| hash.clone())
+    }
+
+}
+
+
+pub struct TextureCache {
+    dir       : PathBuf,
+    max_bytes : u64,
+    index     : Mutex<TextureIndex>
+}
+
+impl TextureCache {
+
+    pub fn from_env() -> Self {
+        let dir       = dotenv::try_var("TEXTURE_CACHE_DIR").unwrap_or(DEFAULT_CACHE_DIR);
+        let max_bytes = dotenv::try_var("TEXTURE_CACHE_MAX_BYTES").map_or(DEFAULT_CACHE_MAX_BYTES, |max_bytes| {
+            max_bytes.parse::<u64>().expect("TEXTURE_CACHE_MAX_BYTES must be a whole number of bytes")
+        });
+        Self::new(PathBuf::from(dir), max_bytes)
+    }
+
+    pub fn new(dir : PathBuf, max_bytes : u64) -> Self {
+        fs::create_dir_all(&dir).unwrap_or_else(|err| panic!("Failed to create texture cache directory {}: {err}", dir.display()));
+        // Files left over from a previous run are ordered by modification time, so the oldest are evicted first.
+        let mut existing = fs::read_dir(&dir).unwrap_or_else(|err| panic!("Failed to read texture cache directory {}: {err}", dir.display()))
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let name  = entry.file_name().into_string().ok()?;
+                let Some((hash, digest)) = name.strip_suffix(".png").and_then(|name| name.split_once('.'))
+                    .filter(|(hash, digest)| is_valid_hash(hash) && is_valid_hash(digest))
+                else {
+                    // Interrupted writes and files from before digests were recorded.
+                    _ = fs::remove_file(entry.path());
+                    return None;
+                };
+                let metadata = entry.metadata().ok()?;
+                Some((metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH), hash.to_string(), digest.to_string(), metadata.len()))
+            })
+            .collect::<Vec<_>>();
+        existing.sort();
+        let mut index = TextureIndex { entries : HashMap::new(), total_bytes : 0, tick : 0 };
+        for (_, hash, digest, bytes) in existing {
+            if let Some(previous) = index.insert(hash.clone(), digest, bytes) {
+                _ = fs::remove_file(dir.join(format!("{hash}.{}.png", previous.digest)));
+            }
+        }
+        Self { dir, max_bytes, index : Mutex::new(index) }
+    }
+
+    fn path(&self, hash : &str, digest : &str) -> PathBuf {
+        self.dir.join(format!("{hash}.{digest}.png"))
+    }
+
+    pub async fn fetch(&self, client : &Client, texture_url : &str) -> surf::Result<DynamicImage> {
+        let hash = skin::texture_id(texture_url);
+        if (! is_valid_hash(hash)) {
+            return image::decode(image::fetch_bytes(client, texture_url).await?);
+        }
+        if let Some(texture) = self.load(hash).await {
+            return Ok(texture);
+        }
+        let bytes   = image::fetch_bytes(client, texture_url).await?;
+        let texture = image::decode(bytes.clone())?;
+        self.store(hash, &bytes).await;
+        Ok(texture)
+    }
+
+    async fn load(&self, hash : &str) -> Option<DynamicImage> {
+        let digest = self.index.lock().await.entries.get(hash)?.digest.clone();
+        let path   = self.path(hash, &digest);
+        // Anything truncated or changed on disk since it was stored is dropped and fetched again.
+        if let Ok(bytes) = smol::fs::read(&path).await
+            && (hex_digest(&bytes) == digest)
+            && let Ok(texture) = image::decode(bytes)
+        {
+            self.index.lock().await.touch(hash);
+            return Some(texture);
+        }
+        _ = smol::fs::remove_file(&path).await;
+        let mut index = self.index.lock().await;
+        if (index.entries.get(hash).is_some_and(|entry| entry.digest == digest)) {
+            index.remove(hash);
+        }
+        None
+    }
+
+    async fn store(&self, hash : &str, bytes : &[u8]) {
+        let digest = hex_digest(bytes);
+        let path   = self.path(hash, &digest);
+        // Concurrent fetches of the same texture each write their own temporary file.
+        let temp   = self.dir.join(format!("{hash}.{:016x}.tmp", rand::random::<u64>()));
+        if (smol::fs::write(&temp, bytes).await.is_err() || smol::fs::rename(&temp, &path).await.is_err()) {
+            _ = smol::fs::remove_file(&temp).await;
+            return;
+        }
+        let mut stale = Vec::new();
+        {
+            let mut index = self.index.lock().await;
+            if let Some(previous) = index.insert(hash.to_string(), digest.clone(), bytes.len() as u64)
+                && (previous.digest != digest)
+            {
+                stale.push(self.path(hash, &previous.digest));
+            }
+            while (index.total_bytes > self.max_bytes) {
+                let Some(evicted) = index.least_recently_used() else { break; };
+                if let Some(entry) = index.remove(&evicted) {
+                    stale.push(self.path(&evicted, &entry.digest));
+                }
+            }
+        }
+        for path in stale {
+            _ = smol::fs::remove_file(path).await;
+        }
+    }
+
+}
+
+
+// Mojang texture hashes are hex digests; anything else is not safe to use as a file name.
+fn is_valid_hash(hash : &str) -> bool {
+    (1..=128).contains(&hash.len()) && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn hex_digest(bytes : &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::image::RgbaImage;
+
+    fn temp_cache(max_bytes : u64) -> TextureCache {
+        let dir = std::env::temp_dir().join(format!("texture-cache-{:016x}", rand::random::<u64>()));
+        TextureCache::new(dir, max_bytes)
+    }
+
+    fn texture(width : u32) -> Vec<u8> {
+        image::to_png(&RgbaImage::new(width, 32)).unwrap()
+    }
+
+    #[test]
+    fn reloads_stored_textures() { smol::block_on(async {
+        let cache = temp_cache(DEFAULT_CACHE_MAX_BYTES);
+        cache.store("abc", &texture(64)).await;
+        assert_eq!(cache.load("abc").await.unwrap().width(), 64);
+        // A fresh cache over the same directory picks the file back up.
+        let reopened = TextureCache::new(cache.dir.clone(), DEFAULT_CACHE_MAX_BYTES);
+        assert_eq!(reopened.load("abc").await.unwrap().width(), 64);
+    }) }
+
+    #[test]
+    fn drops_corrupted_textures() { smol::block_on(async {
+        let cache  = temp_cache(DEFAULT_CACHE_MAX_BYTES);
+        let bytes  = texture(64);
+        let digest = hex_digest(&bytes);
+        cache.store("abc", &bytes).await;
+        fs::write(cache.path("abc", &digest), texture(128)).unwrap();
+        assert!(cache.load("abc").await.is_none());
+        assert!(! cache.path("abc", &digest).exists());
+        assert!(cache.index.lock().await.entries.is_empty());
+    }) }
+
+    #[test]
+    fn evicts_least_recently_used() { smol::block_on(async {
+        let size  = texture(64).len() as u64;
+        let cache = temp_cache(size * 2);
+        cache.store("a", &texture(64)).await;
+        cache.store("b", &texture(64)).await;
+        assert!(cache.load("a").await.is_some());
+        cache.store("c", &texture(64)).await;
+        assert!(cache.load("b").await.is_none());
+        assert!(cache.load("a").await.is_some());
+        assert!(cache.load("c").await.is_some());
+        assert_eq!(fs::read_dir(&cache.dir).unwrap().count(), 2);
+    }) }
+
+    #[test]
+    fn clears_leftover_files() {
+        let dir = std::env::temp_dir().join(format!("texture-cache-{:016x}", rand::random::<u64>()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("abc.0123456789abcdef.tmp"), b"partial").unwrap();
+        fs::write(dir.join("abc.png"), b"unverified").unwrap();
+        let cache = TextureCache::new(dir, DEFAULT_CACHE_MAX_BYTES);
+        assert_eq!(fs::read_dir(&cache.dir).unwrap().count(), 0);
+    }
+
+}
@@ -0,0 +1,168 @@
+use std::sync::LazyLock;
+pub use fluent_bundle::FluentArgs;
+use fluent_bundle::{
+    FluentResource,
+    concurrent::FluentBundle
+};
+use fluent_syntax::ast::Entry;
+use fluent_langneg::{
+    NegotiationStrategy,
+    accepted_languages,
+    negotiate_languages
+};
+use unic_langid::LanguageIdentifier;
+
+
+// The first catalog is the default, and the reference the others are checked against.
+// The second column is the `language_TERRITORY` form Open Graph expects.
+const CATALOG_SOURCES : &[(&str, &str, &str)] = &[
+    ("en", "en_US", include_str!(concat!(env!("CRATE_ROOT"), "/assets/locale/en/main.ftl"))),
+    ("de", "de_DE", include_str!(concat!(env!("CRATE_ROOT"), "/assets/locale/de/main.ftl")))
+];
+
+
+struct Catalog {
+    id        : &'static str,
+    og_locale : &'static str,
+    langid    : LanguageIdentifier,
+    keys      : Vec<String>,
+    bundle    : FluentBundle<FluentResource>
+}
+
+static CATALOGS : LazyLock<Vec<Catalog>> = LazyLock::new(|| CATALOG_SOURCES.iter().map(|&(id, og_locale, source)| {
+    let langid   = id.parse::<LanguageIdentifier>().unwrap();
+    let resource = FluentResource::try_new(source.to_string())
+        .unwrap_or_else(|(_, errors)| panic!("Failed to parse locale {id}: {errors:?}"));
+    let keys     = resource.entries().filter_map(|entry| match (entry) {
+        Entry::Message(message) => Some(message.id.name.to_string()),
+        _                       => None
+    }).collect();
+    let mut bundle = FluentBundle::new_concurrent(vec![ langid.clone() ]);
+    // Isolation marks would end up inside attributes and error messages.
+    bundle.set_use_isolating(false);
+    bundle.add_resource(resource).unwrap_or_else(|errors| panic!("Failed to load locale {id}: {errors:?}"));
+    Catalog { id, og_locale, langid, keys, bundle }
+}).collect());
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Locale(usize);
+
+impl Locale {
+
+    pub fn all() -> impl Iterator<Item = Locale> {
+        (0..CATALOGS.len()).map(Locale)
+    }
+
+    pub fn from_id(id : &str) -> Option<Self> {
+        CATALOGS.iter().position(|catalog| catalog.id == id).map(Locale)
+    }
+
+    pub fn negotiate(accept_language : &str) -> Self {
+        let requested = accepted_languages::parse(accept_language);
+        let available = CATALOGS.iter().map(|catalog| catalog.langid.clone()).collect::<Vec<_>>();
+        let default   = &CATALOGS[0].langid;
+        negotiate_languages(&requested, &available, Some(default), NegotiationStrategy::Lookup).first()
+            .and_then(|langid| CATALOGS.iter().position(|catalog| &catalog.langid == *langid))
+            .map_or_else(Self::default, Locale)
+    }
+
+    pub fn id(self) -> &'static str {
+        CATALOGS[self.0].id
+    }
+
+    pub fn og_locale(self) -> &'static str {
+        CATALOGS[self.0].og_locale
+    }
+
+    pub fn name(self) -> String {
+        self.text("locale-name", None)
+    }
+
+    // Falls back to the default catalog, then to the key itself, so a missing string never breaks a page.
+    pub fn text(self, key : &str, args : Option<&FluentArgs>) -> String {
+        for catalog in [ &CATALOGS[self.0], &CATALOGS[0] ] {
+            if let Some(pattern) = catalog.bundle.get_message(key).and_then(|message| message.value()) {
+                let mut errors = Vec::new();
+                return catalog.bundle.format_pattern(pattern, args, &mut errors).into_owned();
+            }
+        }
+        key.to_string()
+    }
+
+}
+
+
+pub macro t {
+    ($locale:expr, $key:literal) => {
+        ($locale).text($key, None)
+    },
+    ($locale:expr, $key:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = crate::i18n::FluentArgs::new();
+        $( args.set(::core::stringify!($name), $value); )+
+        ($locale).text($key, Some(&args))
+    }}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::site::pages;
+    use std::{ fs, path::Path };
+
+    // Every literal key passed to `t!` in the sources.
+    fn used_keys(dir : &Path, keys : &mut Vec<String>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if (path.is_dir()) { used_keys(&path, keys); continue; }
+            if (path.extension().is_none_or(|ext| ext != "rs")) { continue; }
+            let source = fs::read_to_string(&path).unwrap();
+            for (start, _) in source.match_indices("t!(") {
+                // Skip `assert!(`, `concat!(` and the like.
+                if (source[..start].ends_with(|ch : char| ch.is_alphanumeric() || ch == '_')) { continue; }
+                let call = &source[(start + 3)..];
+                if let Some((locale, rest)) = call.split_once(',')
+                    && (! locale.contains(['"', '\n']))
+                    && let Some(rest) = rest.trim_start().strip_prefix('"')
+                    && let Some((key, _)) = rest.split_once('"')
+                {
+                    keys.push(key.to_string());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn catalogs_have_the_same_keys() {
+        let (default, others) = CATALOGS.split_first().unwrap();
+        for catalog in others {
+            let missing = default.keys.iter().filter(|key| ! catalog.bundle.has_message(key)).collect::<Vec<_>>();
+            assert!(missing.is_empty(), "Locale {} is missing keys: {missing:?}", catalog.id);
+            let extra = catalog.keys.iter().filter(|key| ! default.bundle.has_message(key)).collect::<Vec<_>>();
+            assert!(extra.is_empty(), "Locale {} has keys missing from {}: {extra:?}", catalog.id, default.id);
+        }
+    }
+
+    #[test]
+    fn og_locales_match_their_catalogs() {
+        for catalog in CATALOGS.iter() {
+            let (language, territory) = catalog.og_locale.split_once('_').unwrap();
+            assert_eq!(language, catalog.langid.language.as_str(), "Locale {} has the wrong og:locale", catalog.id);
+            assert!(territory.len() == 2 && territory.chars().all(|ch| ch.is_ascii_uppercase()), "Locale {} has the wrong og:locale", catalog.id);
+        }
+    }
+
+    #[test]
+    fn catalogs_have_used_keys() {
+        let mut keys = vec![ "locale-name".to_string() ];
+        keys.extend(pages::PAGES.iter().map(|page| page.title.to_string()));
+        used_keys(&Path::new(env!("CRATE_ROOT")).join("src"), &mut keys);
+        assert!(keys.len() > 1);
+        for catalog in CATALOGS.iter() {
+            let missing = keys.iter().filter(|key| ! catalog.bundle.has_message(key)).collect::<Vec<_>>();
+            assert!(missing.is_empty(), "Locale {} is missing keys used in the sources: {missing:?}", catalog.id);
+        }
+    }
+
+}
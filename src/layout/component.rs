@@ -0,0 +1,414 @@
+use maud::{ Markup, Render, html };
+
+
+// Centres its content in the page body.
+pub struct Centre<B : Render>(pub B);
+
+impl<B : Render> Render for Centre<B> {
+    fn render(&self) -> Markup {
+        html!{ div .content_centre { (self.0) } }
+    }
+}
+
+
+pub struct Card<B : Render> {
+    pub title : Option<String>,
+    pub body  : B
+}
+
+impl<B : Render> Render for Card<B> {
+    fn render(&self) -> Markup {
+        html!{
+            section .card {
+                @if let Some(title) = &self.title {
+                    h2 .card_title { (title) }
+                }
+                div .card_body { (self.body) }
+            }
+        }
+    }
+}
+
+
+pub struct Table {
+    pub headers : Vec<String>,
+    pub rows    : Vec<Vec<Markup>>
+}
+
+impl Render for Table {
+    fn render(&self) -> Markup {
+        html!{
+            table .table {
+                @if (! self.headers.is_empty()) {
+                    thead { tr { @for header in &self.headers { th scope="col" { (header) } } } }
+                }
+                tbody {
+                    @for row in &self.rows {
+                        tr { @for cell in row { td { (cell) } } }
+                    }
+                }
+            }
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ButtonStyle {
+    Primary,
+    Secondary,
+    Danger,
+    // No styling of its own, for buttons that sit inside another component.
+    Plain
+}
+
+impl ButtonStyle {
+    fn class(self) -> &'static str {
+        match (self) {
+            ButtonStyle::Primary   => "button button_primary",
+            ButtonStyle::Secondary => "button button_secondary",
+            ButtonStyle::Danger    => "button button_danger",
+            ButtonStyle::Plain     => "button_plain"
+        }
+    }
+}
+
+// A link that looks like a `Button`.
+pub struct ButtonLink<'l> {
+    pub href  : &'l str,
+    pub label : String,
+    pub icon  : Option<Markup>,
+    pub style : ButtonStyle
+}
+
+impl Render for ButtonLink<'_> {
+    fn render(&self) -> Markup {
+        html!{
+            a class=(self.style.class()) href=(self.href) {
+                @if let Some(icon) = &self.icon { (icon) }
+                span { (self.label) }
+            }
+        }
+    }
+}
+
+// A submit button.
+pub struct Button {
+    pub label : String,
+    pub icon  : Option<Markup>,
+    pub style : ButtonStyle
+}
+
+impl Button {
+    pub fn new(label : String, style : ButtonStyle) -> Self {
+        Self { label, icon : None, style }
+    }
+}
+
+impl Render for Button {
+    fn render(&self) -> Markup {
+        html!{
+            button type="submit" class=(self.style.class()) {
+                @if let Some(icon) = &self.icon { (icon) }
+                span { (self.label) }
+            }
+        }
+    }
+}
+
+
+pub struct SelectOption {
+    pub value    : String,
+    pub label    : String,
+    pub lang     : Option<&'static str>,
+    pub selected : bool
+}
+
+pub enum Input {
+    Text {
+        value      : Option<String>,
+        required   : bool,
+        min_length : Option<usize>,
+        max_length : Option<usize>,
+        pattern    : Option<&'static str>
+    },
+    Select(Vec<SelectOption>)
+}
+
+pub struct Field {
+    pub name  : &'static str,
+    pub label : Option<String>,
+    pub input : Input,
+    // Shown under the input after a rejected submission.
+    pub error : Option<String>
+}
+
+impl Render for Field {
+    fn render(&self) -> Markup {
+        let error_id = format!("{}_error", self.name);
+        html!{
+            div .field .field_invalid[self.error.is_some()] {
+                @if let Some(label) = &self.label {
+                    label .field_label for=(self.name) { (label) }
+                }
+                @match &self.input {
+                    Input::Text { value, required, min_length, max_length, pattern } => {
+                        input id=(self.name) type="text" name=(self.name) value=[value.as_deref()] required[*required]
+                            minlength=[*min_length] maxlength=[*max_length] pattern=[*pattern]
+                            aria-invalid=[self.error.is_some().then_some("true")]
+                            aria-describedby=[self.error.is_some().then_some(&error_id)];
+                    },
+                    Input::Select(options) => {
+                        select id=(self.name) name=(self.name) {
+                            @for option in options {
+                                option value=(option.value) lang=[option.lang] selected[option.selected] { (option.label) }
+                            }
+                        }
+                    }
+                }
+                @if let Some(error) = &self.error {
+                    span .field_error id=(error_id) { (error) }
+                }
+            }
+        }
+    }
+}
+
+// A POST form. `csrf` is the output of `layout::csrf_field!`.
+pub struct Form<'l> {
+    pub action : &'l str,
+    pub csrf   : Markup,
+    pub fields : Vec<Field>,
+    pub submit : Button,
+    // Lays the fields and button out on one line.
+    pub inline : bool
+}
+
+impl Render for Form<'_> {
+    fn render(&self) -> Markup {
+        html!{
+            form .form .form_inline[self.inline] method="post" action=(self.action) {
+                (self.csrf)
+                @for field in &self.fields { (field) }
+                (self.submit)
+            }
+        }
+    }
+}
+
+
+pub struct Tab<'l> {
+    pub href   : &'l str,
+    pub label  : String,
+    pub active : bool
+}
+
+pub struct Tabs<'l>(pub Vec<Tab<'l>>);
+
+impl Render for Tabs<'_> {
+    fn render(&self) -> Markup {
+        html!{
+            nav .tabs {
+                @for tab in &self.0 {
+                    a .tab .tab_active[tab.active] href=(tab.href) aria-current=[tab.active.then_some("page")] { (tab.label) }
+                }
+            }
+        }
+    }
+}
+
+
+// Opened by linking to `#id`. Uses `:target`, so it works without scripts.
+pub struct Modal<'l, B : Render> {
+    pub id          : &'l str,
+    pub title       : String,
+    pub close_label : String,
+    pub body        : B
+}
+
+impl<B : Render> Render for Modal<'_, B> {
+    fn render(&self) -> Markup {
+        html!{
+            div .modal id=(self.id) role="dialog" aria-modal="true" aria-labelledby=(format!("{}_title", self.id)) {
+                div .modal_box {
+                    div .modal_header {
+                        strong id=(format!("{}_title", self.id)) { (self.title) }
+                        a .modal_close href="#" aria-label=(self.close_label) { "\u{d7}" }
+                    }
+                    div .modal_body { (self.body) }
+                }
+            }
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Info,
+    Success,
+    Warning,
+    Error
+}
+
+impl ToastKind {
+    fn class(self) -> &'static str {
+        match (self) {
+            ToastKind::Info    => "toast toast_info",
+            ToastKind::Success => "toast toast_success",
+            ToastKind::Warning => "toast toast_warning",
+            ToastKind::Error   => "toast toast_error"
+        }
+    }
+}
+
+pub struct Toast {
+    pub kind    : ToastKind,
+    pub message : String
+}
+
+impl Render for Toast {
+    fn render(&self) -> Markup {
+        let role = if (self.kind == ToastKind::Error) { "alert" } else { "status" };
+        html!{ div class=(self.kind.class()) role=(role) { (self.message) } }
+    }
+}
+
+
+pub struct IconLink<'l> {
+    pub href     : &'l str,
+    pub icon     : Markup,
+    pub label    : String,
+    pub external : bool
+}
+
+impl Render for IconLink<'_> {
+    fn render(&self) -> Markup {
+        html!{
+            a href=(self.href) target=[self.external.then_some("_blank")] {
+                (self.icon)
+                span { (self.label) }
+            }
+        }
+    }
+}
+
+// A column of full-width rows, each an `IconLink` or a form with a `ButtonStyle::Plain` button.
+pub struct IconRows(pub Vec<Markup>);
+
+impl Render for IconRows {
+    fn render(&self) -> Markup {
+        html!{ div .icon_rows { @for row in &self.0 { (row) } } }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(component : impl Render) -> String {
+        component.render().into_string()
+    }
+
+    #[test]
+    fn centre() {
+        assert_eq!(snapshot(Centre("text")), r#"<div class="content_centre">text</div>"#);
+    }
+
+    #[test]
+    fn card() {
+        assert_eq!(snapshot(Card { title : Some("Title".to_string()), body : "body" }), r#"<section class="card"><h2 class="card_title">Title</h2><div class="card_body">body</div></section>"#);
+        assert_eq!(snapshot(Card { title : None, body : "body" }), r#"<section class="card"><div class="card_body">body</div></section>"#);
+    }
+
+    #[test]
+    fn table() {
+        let table = Table {
+            headers : vec![ "A".to_string(), "B".to_string() ],
+            rows    : vec![ vec![ html!{ "1" }, html!{ b { "2" } } ] ]
+        };
+        assert_eq!(snapshot(table), r#"<table class="table"><thead><tr><th scope="col">A</th><th scope="col">B</th></tr></thead><tbody><tr><td>1</td><td><b>2</b></td></tr></tbody></table>"#);
+        assert_eq!(snapshot(Table { headers : Vec::new(), rows : Vec::new() }), r#"<table class="table"><tbody></tbody></table>"#);
+    }
+
+    #[test]
+    fn button_link() {
+        let link = ButtonLink { href : "/a?b&c", label : "Go".to_string(), icon : Some(html!{ svg {} }), style : ButtonStyle::Primary };
+        assert_eq!(snapshot(link), r#"<a class="button button_primary" href="/a?b&amp;c"><svg></svg><span>Go</span></a>"#);
+    }
+
+    #[test]
+    fn button() {
+        assert_eq!(snapshot(Button::new("Save".to_string(), ButtonStyle::Secondary)), r#"<button type="submit" class="button button_secondary"><span>Save</span></button>"#);
+        assert_eq!(snapshot(Button { label : "Unlink".to_string(), icon : Some(html!{ svg {} }), style : ButtonStyle::Plain }), r#"<button type="submit" class="button_plain"><svg></svg><span>Unlink</span></button>"#);
+    }
+
+    #[test]
+    fn field() {
+        let text = Field {
+            name  : "username",
+            label : Some("Username".to_string()),
+            input : Input::Text { value : Some("<b>".to_string()), required : true, min_length : Some(3), max_length : Some(16), pattern : Some("[a-z]+") },
+            error : Some("Too short".to_string())
+        };
+        assert_eq!(snapshot(text), r#"<div class="field field_invalid"><label class="field_label" for="username">Username</label><input id="username" type="text" name="username" value="&lt;b&gt;" required minlength="3" maxlength="16" pattern="[a-z]+" aria-invalid="true" aria-describedby="username_error"><span class="field_error" id="username_error">Too short</span></div>"#);
+        let select = Field {
+            name  : "locale",
+            label : None,
+            input : Input::Select(vec![
+                SelectOption { value : "en".to_string(), label : "English".to_string(), lang : Some("en"), selected : false },
+                SelectOption { value : "de".to_string(), label : "Deutsch".to_string(), lang : Some("de"), selected : true }
+            ]),
+            error : None
+        };
+        assert_eq!(snapshot(select), r#"<div class="field"><select id="locale" name="locale"><option value="en" lang="en">English</option><option value="de" lang="de" selected>Deutsch</option></select></div>"#);
+    }
+
+    #[test]
+    fn form() {
+        let form = Form {
+            action : "/dashboard/theme",
+            csrf   : html!{ input type="hidden" name="csrf-token" value="token"; },
+            fields : Vec::new(),
+            submit : Button::new("Save".to_string(), ButtonStyle::Secondary),
+            inline : true
+        };
+        assert_eq!(snapshot(form), r#"<form class="form form_inline" method="post" action="/dashboard/theme"><input type="hidden" name="csrf-token" value="token"><button type="submit" class="button button_secondary"><span>Save</span></button></form>"#);
+    }
+
+    #[test]
+    fn tabs() {
+        let tabs = Tabs(vec![
+            Tab { href : "/a", label : "A".to_string(), active : true },
+            Tab { href : "/b", label : "B".to_string(), active : false }
+        ]);
+        assert_eq!(snapshot(tabs), r#"<nav class="tabs"><a class="tab tab_active" href="/a" aria-current="page">A</a><a class="tab" href="/b">B</a></nav>"#);
+    }
+
+    #[test]
+    fn modal() {
+        let modal = Modal { id : "confirm", title : "Confirm".to_string(), close_label : "Close".to_string(), body : "Sure?" };
+        assert_eq!(snapshot(modal), r##"<div class="modal" id="confirm" role="dialog" aria-modal="true" aria-labelledby="confirm_title"><div class="modal_box"><div class="modal_header"><strong id="confirm_title">Confirm</strong><a class="modal_close" href="#" aria-label="Close">×</a></div><div class="modal_body">Sure?</div></div></div>"##);
+    }
+
+    #[test]
+    fn toast() {
+        assert_eq!(snapshot(Toast { kind : ToastKind::Success, message : "Saved".to_string() }), r#"<div class="toast toast_success" role="status">Saved</div>"#);
+        assert_eq!(snapshot(Toast { kind : ToastKind::Error, message : "Failed".to_string() }), r#"<div class="toast toast_error" role="alert">Failed</div>"#);
+    }
+
+    #[test]
+    fn icon_link() {
+        let link = IconLink { href : "https://example.com", icon : html!{ svg {} }, label : "Example".to_string(), external : true };
+        assert_eq!(snapshot(link), r#"<a href="https://example.com" target="_blank"><svg></svg><span>Example</span></a>"#);
+        let link = IconLink { href : "/", icon : html!{ svg {} }, label : "Home".to_string(), external : false };
+        assert_eq!(snapshot(link), r#"<a href="/"><svg></svg><span>Home</span></a>"#);
+    }
+
+    #[test]
+    fn icon_rows() {
+        assert_eq!(snapshot(IconRows(vec![ html!{ a { "1" } }, html!{ a { "2" } } ])), r#"<div class="icon_rows"><a>1</a><a>2</a></div>"#);
+    }
+
+}
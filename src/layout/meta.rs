@@ -0,0 +1,93 @@
+use crate::{
+    i18n::{ Locale, t },
+    site::{ self, SharedSiteState, assets }
+};
+use tide::Request;
+use maud::{ Markup, html };
+
+
+const SITE_NAME : &str = "PipeworkMC";
+
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Robots {
+    Index,
+    NoIndex
+}
+
+impl Robots {
+    fn content(self) -> &'static str {
+        match (self) {
+            Robots::Index   => "index, follow",
+            Robots::NoIndex => "noindex, nofollow"
+        }
+    }
+}
+
+
+// Everything `layout::default` puts in `<head>` besides stylesheets.
+pub struct PageMeta {
+    pub supertitle  : String,
+    pub title       : String,
+    // Falls back to the site description.
+    pub description : Option<String>,
+    // A path or absolute URL. Falls back to the site logo.
+    pub image       : Option<String>,
+    pub robots      : Robots
+}
+
+impl PageMeta {
+
+    pub fn new(supertitle : impl Into<String>, title : impl Into<String>) -> Self {
+        Self { supertitle : supertitle.into(), title : title.into(), description : None, image : None, robots : Robots::Index }
+    }
+
+    pub fn description(mut self, description : String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    pub fn image(mut self, image : String) -> Self {
+        self.image = Some(image);
+        self
+    }
+
+    pub fn robots(mut self, robots : Robots) -> Self {
+        self.robots = robots;
+        self
+    }
+
+    pub(super) fn head(&self, req : &Request<SharedSiteState>, locale : Locale) -> Markup {
+        let base_url    = site::base_url(req);
+        let title       = if (self.title == SITE_NAME) {
+            format!("{SITE_NAME} \u{2013} {}", self.supertitle)
+        } else {
+            format!("{} \u{2013} {} | {SITE_NAME}", self.title, self.supertitle)
+        };
+        let description = self.description.clone().unwrap_or_else(|| t!(locale, "meta-description"));
+        let canonical   = format!("{base_url}{}", req.url().path());
+        let logo        = assets::url("icon/brand/pipework.png");
+        let image       = self.image.as_deref().unwrap_or(&logo);
+        let image       = if (image.starts_with('/')) { format!("{base_url}{image}") } else { image.to_string() };
+        html!{
+            title { (title) }
+            meta name="description" content=(description);
+            meta name="robots" content=(self.robots.content());
+            link rel="canonical" href=(canonical);
+            link rel="icon" type="image/png" href=(logo);
+            link rel="apple-touch-icon" href=(logo);
+            meta property="og:site_name" content=(SITE_NAME);
+            meta property="og:type" content="website";
+            meta property="og:locale" content=(locale.og_locale());
+            meta property="og:title" content=(title);
+            meta property="og:description" content=(description);
+            meta property="og:url" content=(canonical);
+            meta property="og:image" content=(image);
+            meta name="twitter:card" content="summary";
+            meta name="twitter:title" content=(title);
+            meta name="twitter:description" content=(description);
+            meta name="twitter:image" content=(image);
+        }
+    }
+
+}
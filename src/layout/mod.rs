@@ -1,16 +1,27 @@
-use crate::site::SharedSiteState;
+use crate::{
+    i18n::t,
+    layout::component::{ Centre, IconLink, IconRows, Toast },
+    site::{ self, SharedSiteState, avatar, community, flash, pages, theme::Theme }
+};
 use pipeworkmc_db::LoginSession;
 use std::sync::Arc;
-use tide::Request;
+use tide::{
+    Request,
+    Response,
+    StatusCode
+};
 pub use maud::{
     DOCTYPE,
+    Markup,
     PreEscaped,
     Render
 };
 use chrono::{ Datelike, Utc };
 
 
-const STEVE_SKIN : &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAgAAAAICAYAAADED76LAAAAAXNSR0IB2cksfwAAAARnQU1BAACxjwv8YQUAAAAgY0hSTQAAeiYAAICEAAD6AAAAgOgAAHUwAADqYAAAOpgAABdwnLpRPAAAAAlwSFlzAAAuIwAALiMBeKU/dgAAAAd0SU1FB+kICQEQGZ+MNAcAAADPSURBVBjTTcyrbgJBAIXhn83MLC0ssxWQPkANwRAwdcXyADxBTV8GX4LAkSZVBIElBENIKmoQyGaraLLDZe/J1i0ceU7OV+o+PeQAVSW4zTnJiJIMy5zDorgdq0pQVgJhK8nbyzOPjTri3iELToRxijE+o9WG0vi1n2vtcmdLAMI4LSRjfCytXQbDKZN1q3h+bNsMhlO0dq8CwPJrB0Cv07wKlyDAO3hM5gscS+JYkvfPGd7B49f3EcLO+TulxFHC98+eOEqwy4rjJaVWkfwD+DdX0n69wvwAAAAASUVORK5CYII=";
+pub mod component;
+pub mod meta;
+pub use meta::{ PageMeta, Robots };
 
 
 #[derive(PartialEq, Eq)]
@@ -21,50 +32,94 @@ pub enum PageType {
 
 
 pub async fn default(
-    req        : &mut Request<SharedSiteState>,
-    page_type  : PageType,
-    login      : Option<&LoginSession>,
-    supertitle : impl Render,
-    title      : impl Render,
-    main       : impl Render
+    req       : &mut Request<SharedSiteState>,
+    page_type : PageType,
+    login     : Option<&LoginSession>,
+    meta      : PageMeta,
+    main      : impl Render
 ) -> PreEscaped<String> {
+    let locale           = site::locale(req);
     let mut has_account  = false;
-    let mut account_name = html!{ "No" (NBSP) "Account" };
-    let mut account_icon = html!{ (icon_svg!("account.svg")) };
+    let mut account_name = ::maud::html!{ (t!(locale, "layout-no-account").replace(' ', "\u{a0}")) };
+    let mut account_icon = ::maud::html!{ (icon_svg!("account.svg")) };
+    let mut account_cape = None;
+    let mut theme        = Theme::System;
+    let flashes          = flash::take(req);
+    let current_page     = if (page_type == PageType::Normal) { pages::current(req.url().path()) } else { None };
+    let meta             = if (page_type == PageType::Error) { meta.robots(Robots::NoIndex) } else { meta };
+    let head             = meta.head(req, locale);
     if let Some(login) = login {
         has_account  = true;
-        account_name = html!{ (login.minecraft_username) };
-        account_icon = html!{ img src=(login.minecraft_skin.as_ref().map_or(STEVE_SKIN, |s| s.as_str())); };
+        account_name = ::maud::html!{ (login.minecraft_username) };
+        if let Some(minecraft_uuid) = site::session_minecraft_uuid(req) {
+            let profile  = req.state().profiles().lookup(minecraft_uuid).await;
+            if let Some(profile) = &profile {
+                account_name = ::maud::html!{ (profile.username) };
+            }
+            account_icon = ::maud::html!{ img src=(avatar::avatar_url(minecraft_uuid, profile.as_ref().and_then(|profile| profile.skin.as_ref()), 64)); };
+            if let Some(cape) = profile.as_ref().and_then(|profile| profile.cape.as_ref()) {
+                let alias    = cape.alias.clone().unwrap_or_else(|| t!(locale, "layout-cape"));
+                account_cape = Some(::maud::html!{ img .header_cape src=(avatar::cape_url(minecraft_uuid, cape, 32)) alt=(alias) title=(alias); });
+            }
+            theme        = req.state().lookup_theme(minecraft_uuid).await;
+        }
     }
 
-    html!{
+    ::maud::html!{ (DOCTYPE) html lang=(locale.id()) data-theme=[theme.attribute()] {
 
         head {
-            (stylesheet!("main.css"))
+            meta charset="utf-8";
+            meta name="viewport" content="width=device-width, initial-scale=1";
+            (head)
+            meta name="color-scheme" content=(theme.color_scheme());
+            (stylesheet!(req, "main.css"))
+            (stylesheet!(req, "components.css"))
         }
 
         body {
 
             div #header {
                 div #header_page .header_error[page_type == PageType::Error] {
-                    span #header_page_supertitle { (supertitle) } (NBSP)
+                    span #header_page_supertitle { (meta.supertitle) } (NBSP)
                     br;
-                    span #header_page_title { (title) } (NBSP)
+                    span #header_page_title { (meta.title) } (NBSP)
                 }
                 div #header_logo {
                     "PIPEW"
-                    (icon_png_b64!("brand/pipework.png.b64"))
+                    (icon_png!("brand/pipework.png"))
                      "RK"
                     span { "MC" }
                 }
                 div #header_account {
                     span .no_account[! has_account] { (account_name) }
                     (account_icon)
+                    @if let Some(account_cape) = account_cape {
+                        (account_cape)
+                    }
                 }
             }
 
+            (pages::render_header(locale, login, current_page))
+
             div #main {
-                (main)
+                @if let Some(breadcrumbs) = pages::render_breadcrumbs(locale, current_page) {
+                    (breadcrumbs)
+                }
+                div #main_body {
+                    @if let Some(sidebar) = pages::render_sidebar(locale, login, current_page) {
+                        (sidebar)
+                    }
+                    div #main_content {
+                        @if (! flashes.is_empty()) {
+                            div #flashes {
+                                @for flash in flashes {
+                                    (Toast { kind : flash.level.into(), message : flash.message })
+                                }
+                            }
+                        }
+                        (main)
+                    }
+                }
             }
 
             div #footer {
@@ -74,13 +129,15 @@ pub async fn default(
                         (icon_svg!("home.svg"))
                     }
                     (NBSP)
-                    a .pad6 href="https://github.com/PipeworkMC" target="_blank" {
+                    a .pad6 href=(community::GITHUB_URL) target="_blank" {
                         (icon_svg!("brand/github.svg"))
                     }
-                    a .pad6 target="_blank" {
-                        (icon_svg!("brand/matrix.svg"))
+                    @if let Some(matrix_url) = community::matrix_url() {
+                        a .pad6 href=(matrix_url) target="_blank" {
+                            (icon_svg!("brand/matrix.svg"))
+                        }
                     }
-                    a href="mailto:pipeworkmc@duck.com" target="_blank" {
+                    a href=(format!("mailto:{}", community::CONTACT_EMAIL)) target="_blank" {
                         (icon_svg!("mail.svg"))
                     }
                 }
@@ -93,12 +150,33 @@ pub async fn default(
                         "Totobird-Creations"
                     }
                 }
-                div #footer_noassoc { "NOT AN OFFICIAL MINECRAFT SERVICE. NOT APPROVED BY OR ASSOCIATED WITH MOJANG OR MICROSOFT." }
+                div #footer_noassoc { (t!(locale, "layout-not-official")) }
             }
 
         }
 
-    }
+    } }
+}
+
+
+// Shared by `handled!` and the middleware that answers before a route runs.
+pub async fn error_page(req : &mut Request<SharedSiteState>, status : StatusCode, message : String) -> Response {
+    let login  = Arc::clone(req.state()).lookup_login_session(req).await;
+    let locale = site::locale(req);
+    let mut res = Response::from(default(req,
+        PageType::Error,
+        login.as_deref(),
+        PageMeta::new(status.canonical_reason(), (status as usize).to_string()),
+        Centre(::maud::html!{
+            p { strong { (message) } }
+            br;
+            (IconRows([ &pages::HOME, &pages::DASHBOARD ].into_iter().map(|page| {
+                IconLink { href : page.path, icon : page.icon().unwrap_or(PreEscaped(String::new())), label : page.title(locale), external : false }.render()
+            }).collect()))
+        })
+    ).await);
+    res.set_status(status);
+    res
 }
 
 
@@ -111,12 +189,30 @@ pub macro html($($tt:tt)*) {
     }
 }
 
-pub macro stylesheet($path:tt) {
-    ::maud::html!{ style { (::maud::PreEscaped(::core::include_str!(::core::concat!(::core::env!("CRATE_ROOT"), "/assets/stylesheet/", $path)))) } }
+// Fails to compile if the asset does not exist, like the `include_str!` these references replaced.
+pub macro asset_path($path:expr) {{
+    const _ : &[u8] = ::core::include_bytes!(::core::concat!(::core::env!("CRATE_ROOT"), "/assets/", $path));
+    $path
+}}
+
+// `style-src` only trusts the request nonce, so stylesheets must carry it.
+pub macro stylesheet($req:expr, $path:tt) {
+    ::maud::html!{ link rel="stylesheet" nonce=[crate::site::csp_nonce($req)] href=(crate::site::assets::url(asset_path!(::core::concat!("stylesheet/", $path)))); }
 }
 pub macro icon_svg($path:tt) {
-    ::maud::PreEscaped(::core::include_str!(::core::concat!(::core::env!("CRATE_ROOT"), "/assets/icon/", $path)))
+    crate::site::assets::svg_icon(asset_path!(::core::concat!("icon/", $path)), None)
+}
+// Both variants are sent; the stylesheet hides whichever does not match the active theme.
+pub macro themed_icon_svg($dark:tt, $light:tt) {
+    ::maud::html!{
+        (crate::site::assets::svg_icon(asset_path!(::core::concat!("icon/", $dark)), Some("theme_dark")))
+        (crate::site::assets::svg_icon(asset_path!(::core::concat!("icon/", $light)), Some("theme_light")))
+    }
+}
+// Every state-changing form needs this, or `CsrfMiddleware` rejects the post.
+pub macro csrf_field($req:expr) {
+    ::maud::html!{ input type="hidden" name=(crate::site::middleware::CSRF_FIELD) value=(crate::site::middleware::csrf_token($req)); }
 }
-pub macro icon_png_b64($path:tt) {
-    ::maud::html!{ img src=(::core::concat!("data:image/png;base64,", ::core::include_str!(::core::concat!(::core::env!("CRATE_ROOT"), "/assets/icon/", $path)))); }
+pub macro icon_png($path:tt) {
+    ::maud::html!{ img src=(crate::site::assets::url(asset_path!(::core::concat!("icon/", $path)))); }
 }
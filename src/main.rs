@@ -10,18 +10,19 @@ use tide::{
     Request,
     Response,
     StatusCode,
-    sessions::{
-        SessionMiddleware,
-        CookieStore
-    }
+    http::Method
 };
 use tide_rustls::TlsListener;
 
 
 mod auth;
+mod cache;
+mod i18n;
+mod ping;
 
 mod layout;
 mod site;
+mod skin;
 use site::{ SiteState, SharedSiteState };
 
 mod util;
@@ -30,6 +31,10 @@ use util::dotenv;
 
 fn main() -> tide::Result<()> { smol::block_on(async {
     unsafe { dotenv::load(); }
+    #[cfg(feature = "dev-login")]
+    if (auth::dev::is_enabled()) {
+        auth::dev::verify_environment();
+    }
 
     let mut db_addr      = dotenv::var("DATABASE_ADDRRESS").split(":");
     let     db_addr_host = db_addr.next().unwrap();
@@ -37,26 +42,82 @@ fn main() -> tide::Result<()> { smol::block_on(async {
     let     db_addr_port = if (db_addr_port.is_empty()) { 5432 } else { db_addr_port.parse::<u16>().unwrap() };
     let     db = PipeworkDb::connect(db_addr_host, db_addr_port).await.unwrap();
 
-    let mut app = tide::with_state(SiteState::new(db));
-
-    app.with(SessionMiddleware::new(
-        CookieStore,
-        dotenv::var("SESSION_SECRET").as_bytes()
-    )
-        .with_cookie_name("pipeworkmc")
+    let state = SiteState::new(db);
+    state.profiles().spawn_refresher();
+    state.status().spawn_poller();
+    let session = site::middleware::SessionMiddleware::from_env(&state);
+
+    let mut app = tide::with_state(state);
+
+    app.with(site::middleware::CompressionMiddleware);
+    app.with(site::middleware::SecurityHeadersMiddleware);
+
+    app.with(session);
+    // Before `CsrfMiddleware`, so its error pages are translated.
+    app.with(site::middleware::LocaleMiddleware);
+    app.with(site::middleware::CsrfMiddleware::new()
+        .exempt("/api/")
+        .exempt("/static/")
+        .exempt("/avatar/")
+        .exempt("/cape/")
+        .exempt("/render/")
     );
 
-    app.at("/").get(handled!(site::route_todo));
-
-    app.at("/dashboard/login").get(handled!(site::dashboard::login::route_login));
-    app.at("/dashboard/login/after_oauth").get(handled!(site::dashboard::login::route_after_oauth));
-    app.at("/dashboard").get(handled!(site::dashboard::route_index));
+    app.at(site::pages::HOME.path).get(handled!(site::landing::route_landing));
+
+    app.at("/auth/:provider/start").get(handled!(site::provider::route_start));
+    app.at("/auth/:provider/callback").get(handled!(site::provider::route_callback));
+    app.at("/auth/:provider/callback").post(handled!(site::provider::route_callback));
+    app.at("/dashboard/login/after_oauth").get(handled!(async |req| site::provider::route_legacy_callback(req, "microsoft").await));
+    app.at("/dashboard/github/after_oauth").get(handled!(async |req| site::provider::route_legacy_callback(req, "github").await));
+
+    app.at(site::pages::DASHBOARD_LOGIN.path).get(handled!(site::dashboard::login::route_login));
+    app.at(site::pages::DASHBOARD.path).get(handled!(site::dashboard::route_index));
+    app.at("/dashboard/github/unlink").post(handled!(site::dashboard::github::route_unlink));
+    app.at("/dashboard/theme").post(handled!(site::dashboard::theme::route_theme));
+    app.at("/dashboard/locale").post(handled!(site::dashboard::locale::route_locale));
+
+    app.at("/static/:hash/*path").get(handled!(site::assets::route_static));
+    app.at("/robots.txt").get(handled!(site::seo::route_robots));
+    app.at("/sitemap.xml").get(handled!(site::seo::route_sitemap));
+    app.at("/favicon.ico").get(handled!(site::seo::route_favicon));
+
+    // Images are public, so any origin may draw them onto a canvas.
+    let images_cors = site::middleware::CorsMiddleware::new(site::middleware::CorsConfig::new()
+        .origin("*")
+        .methods(&[ Method::Get ])
+    );
+    app.at("/avatar/:file").with(images_cors.clone()).get(handled!(site::avatar::route_avatar)).options(site::middleware::route_options);
+    app.at("/cape/:file").with(images_cors.clone()).get(handled!(site::avatar::route_cape)).options(site::middleware::route_options);
+    app.at("/render/head/:file").with(images_cors.clone()).get(handled!(site::render::route_head)).options(site::middleware::route_options);
+    app.at("/render/body/:file").with(images_cors).get(handled!(site::render::route_body)).options(site::middleware::route_options);
+
+    {
+        let mut api = app.at("/api/v1");
+        api.with(site::middleware::CorsMiddleware::new(site::middleware::CorsConfig::new()
+            .origins_from_env("CORS_API_ORIGINS")
+            .methods(&[ Method::Get, Method::Post ])
+            .headers(&[ "Content-Type" ])
+            .max_age(3600)
+        ));
+        api.at("*").options(site::middleware::route_options);
+        api.at("/profile").post(api_handled!(site::api::profile::route_by_uuids));
+        api.at("/profile/by-name").post(api_handled!(site::api::profile::route_by_names));
+        api.at("/profile/by-name/:name").get(api_handled!(site::api::profile::route_by_name));
+        api.at("/profile/:uuid").get(api_handled!(site::api::profile::route_by_uuid));
+        api.at("/status").get(api_handled!(site::api::status::route_status));
+    }
 
     app.at("*").get(handled!(async |_| tide::Result::<Response>::Err(tide::Error::from_str(
         StatusCode::NotFound,
         StatusCode::NotFound.canonical_reason()
     ))));
 
+    #[cfg(feature = "dev-login")]
+    if (auth::dev::is_enabled()) {
+        app.listen("127.0.0.1:8080").await?;
+        return Ok(());
+    }
     app.listen(TlsListener::build()
         .addrs("127.0.0.1:8080")
         .cert("cert/pipeworkmc.cert")
@@ -69,49 +130,35 @@ fn main() -> tide::Result<()> { smol::block_on(async {
 macro handled($route:expr) {
     |mut req : Request<SharedSiteState>| async move {
 
-        let mut res = match (($route)(&mut req).await) {
+        let res = match (($route)(&mut req).await) {
             Ok(res)  => Into::<Response>::into(res),
             Err(err) => Response::from(err)
         };
 
         let status = res.status();
         let err    = match (res.error()) {
-            Some(err) => Some(layout::html!{ (err) }),
+            Some(err) => Some(err.to_string()),
             None      => {
                 if (status.is_client_error() || status.is_server_error()) {
-                    Some(layout::html!{ (status.canonical_reason()) })
+                    Some(status.canonical_reason().to_string())
                 } else { None }
             }
         };
 
-        let login = std::sync::Arc::clone(req.state()).lookup_login_session(&mut req).await;
-        if let Some(err_body) = err {
-            res = Response::from(layout::default(&mut req,
-                crate::layout::PageType::Error,
-                login.as_ref().map(|l| &**l),
-                status.canonical_reason(),
-                status as usize,
-                layout::html!{
-                    div .content_centre {
-                        p { strong { (err_body) } }
-                        br;
-                        div .icon_rows {
-                            a href="/" {
-                                (layout::icon_svg!("home.svg"))
-                                span { "Home" }
-                            }
-                            a href="/dashboard" {
-                                (layout::icon_svg!("dashboard.svg"))
-                                span { "Dashboard" }
-                            }
-                        }
-                    }
-                }
-            ).await);
-            res.set_status(status);
-        }
-
-        Ok(res)
+        Ok(match (err) {
+            Some(err_body) => layout::error_page(&mut req, status, err_body).await,
+            None           => res
+        })
 
     }
 }
+
+
+macro api_handled($route:expr) {
+    |mut req : Request<SharedSiteState>| async move {
+        Ok(match (($route)(&mut req).await) {
+            Ok(res)  => Into::<Response>::into(res),
+            Err(err) => site::api::json_response(err.status(), &site::api::ApiError { error : &err.to_string() }, "no-store")?
+        })
+    }
+}
@@ -0,0 +1,83 @@
+use crate::ping::{ ServerAddress, ServerPlayers, ServerStatus, ServerVersion, invalid_data, strip_formatting };
+use std::{
+    io,
+    time::Instant
+};
+use smol::{
+    io::{ AsyncReadExt, AsyncWriteExt },
+    net::TcpStream
+};
+
+
+const PACKET_SERVER_LIST_PING : u8   = 0xfe;
+const PACKET_PLUGIN_MESSAGE   : u8   = 0xfa;
+const PACKET_KICK             : u8   = 0xff;
+const PING_HOST_CHANNEL       : &str = "MC|PingHost";
+const PROTOCOL_VERSION        : u8   = 74;
+
+
+// 1.6 servers understand the `MC|PingHost` form, and older ones stop reading after the first two bytes.
+pub(super) async fn ping(address : &ServerAddress) -> io::Result<ServerStatus> {
+    let mut stream = TcpStream::connect((address.host.as_str(), address.port)).await?;
+    stream.set_nodelay(true)?;
+
+    let host        = address.host.encode_utf16().collect::<Vec<_>>();
+    let mut request = vec![ PACKET_SERVER_LIST_PING, 0x01, PACKET_PLUGIN_MESSAGE ];
+    write_string(&mut request, &PING_HOST_CHANNEL.encode_utf16().collect::<Vec<_>>());
+    request.extend_from_slice(&(7 + 2 * host.len() as u16).to_be_bytes());
+    request.push(PROTOCOL_VERSION);
+    write_string(&mut request, &host);
+    request.extend_from_slice(&(address.port as i32).to_be_bytes());
+
+    let sent_at = Instant::now();
+    stream.write_all(&request).await?;
+    stream.flush().await?;
+
+    let mut header = [0; 3];
+    stream.read_exact(&mut header).await?;
+    let latency = sent_at.elapsed();
+    if (header[0] != PACKET_KICK) {
+        return Err(invalid_data(format!("Expected legacy kick packet, got packet {:#04x}", header[0])));
+    }
+    let mut body = vec![0; u16::from_be_bytes([header[1], header[2]]) as usize * 2];
+    stream.read_exact(&mut body).await?;
+    let body = String::from_utf16(&body.chunks_exact(2).map(|ch| u16::from_be_bytes([ch[0], ch[1]])).collect::<Vec<_>>())
+        .map_err(|_| invalid_data("Legacy kick message is not valid UTF-16"))?;
+
+    parse_kick(&body).map(|mut status| {
+        status.latency = latency;
+        status
+    }).ok_or_else(|| invalid_data("Malformed legacy status response"))
+}
+
+fn write_string(buf : &mut Vec<u8>, value : &[u16]) {
+    buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    for ch in value {
+        buf.extend_from_slice(&ch.to_be_bytes());
+    }
+}
+
+// 1.4 to 1.6 send `§1\0protocol\0version\0motd\0online\0max`; older servers send `motd§online§max`.
+pub(super) fn parse_kick(body : &str) -> Option<ServerStatus> {
+    let (protocol, version, motd, online, max) = match (body.strip_prefix("\u{a7}1\0")) {
+        Some(fields) => {
+            let mut fields = fields.split('\0');
+            let protocol = fields.next()?.parse::<i32>().ok()?;
+            let version  = fields.next()?;
+            (protocol, version, fields.next()?, fields.next()?, fields.next()?)
+        },
+        None => {
+            let mut fields = body.rsplitn(3, '\u{a7}');
+            let max    = fields.next()?;
+            let online = fields.next()?;
+            (0, "", fields.next()?, online, max)
+        }
+    };
+    Some(ServerStatus {
+        version : ServerVersion { name : strip_formatting(version), protocol },
+        players : ServerPlayers { online : online.parse().ok()?, max : max.parse().ok()?, sample : Vec::new() },
+        motd    : strip_formatting(motd),
+        favicon : None,
+        latency : Default::default()
+    })
+}
@@ -0,0 +1,460 @@
+use core::time::Duration;
+use std::{
+    io::{ self, ErrorKind },
+    time::Instant
+};
+use smol::{
+    Timer,
+    future::FutureExt,
+    io::{ AsyncReadExt, AsyncWriteExt },
+    net::TcpStream
+};
+use serde::Deserialize as Deser;
+use uuid::Uuid;
+
+
+mod legacy;
+
+
+pub const DEFAULT_PORT : u16 = 25565;
+
+// -1 asks the server to report its own version instead of refusing an unknown one.
+const STATUS_PROTOCOL_VERSION : i32   = -1;
+const NEXT_STATE_STATUS       : i32   = 1;
+const PACKET_STATUS           : i32   = 0x00;
+const PACKET_PING             : i32   = 0x01;
+// Favicons are sent inline as base64, so responses can be much larger than the 32767 character string limit suggests.
+const MAX_PACKET_LENGTH       : usize = 2 * 1024 * 1024;
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerAddress {
+    pub host : String,
+    pub port : u16
+}
+
+impl ServerAddress {
+
+    pub fn parse(address : &str) -> Option<Self> {
+        let address = address.trim();
+        // A bare IPv6 address has colons of its own, so a port is only split off one in brackets.
+        let (host, port) = match (address.rsplit_once(':')) {
+            Some((host, port)) if (! host.contains(':') || host.ends_with(']')) => (host, port.parse::<u16>().ok()?),
+            _ => (address, DEFAULT_PORT)
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if (host.is_empty()) { return None; }
+        Some(Self { host : host.to_string(), port })
+    }
+
+}
+
+impl core::fmt::Display for ServerAddress {
+    fn fmt(&self, f : &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if (self.port == DEFAULT_PORT) { write!(f, "{}", self.host) }
+        else { write!(f, "{}:{}", self.host, self.port) }
+    }
+}
+
+
+#[derive(Clone, Debug)]
+pub struct ServerStatus {
+    pub version : ServerVersion,
+    pub players : ServerPlayers,
+    pub motd    : String,
+    pub favicon : Option<String>,
+    pub latency : Duration
+}
+
+#[derive(Clone, Debug)]
+pub struct ServerVersion {
+    pub name     : String,
+    pub protocol : i32
+}
+
+#[derive(Clone, Debug)]
+pub struct ServerPlayers {
+    pub online : u32,
+    pub max    : u32,
+    pub sample : Vec<ServerPlayer>
+}
+
+#[derive(Clone, Debug)]
+pub struct ServerPlayer {
+    pub id   : Uuid,
+    pub name : String
+}
+
+
+// Pings a Java Edition server, falling back to the 1.6 protocol for servers that do not understand the modern handshake.
+pub async fn ping(address : &ServerAddress, timeout : Duration) -> io::Result<ServerStatus> {
+    match (with_timeout(ping_modern(address), timeout).await) {
+        Ok(status) => Ok(status),
+        // Nothing is listening, so the legacy protocol would fail the same way.
+        Err(err) if (matches!(err.kind(), ErrorKind::ConnectionRefused | ErrorKind::TimedOut)) => Err(err),
+        Err(err) => with_timeout(legacy::ping(address), timeout).await.map_err(|_| err)
+    }
+}
+
+async fn with_timeout<T>(future : impl Future<Output = io::Result<T>>, timeout : Duration) -> io::Result<T> {
+    future.or(async {
+        Timer::after(timeout).await;
+        Err(io::Error::new(ErrorKind::TimedOut, "Server did not respond in time"))
+    }).await
+}
+
+
+async fn ping_modern(address : &ServerAddress) -> io::Result<ServerStatus> {
+    let mut stream = TcpStream::connect((address.host.as_str(), address.port)).await?;
+    stream.set_nodelay(true)?;
+
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, STATUS_PROTOCOL_VERSION);
+    write_string(&mut handshake, &address.host);
+    handshake.extend_from_slice(&address.port.to_be_bytes());
+    write_varint(&mut handshake, NEXT_STATE_STATUS);
+    write_packet(&mut stream, PACKET_STATUS, &handshake).await?;
+    write_packet(&mut stream, PACKET_STATUS, &[]).await?;
+
+    let (packet_id, body) = read_packet(&mut stream).await?;
+    if (packet_id != PACKET_STATUS) {
+        return Err(invalid_data(format!("Expected status response, got packet {packet_id:#04x}")));
+    }
+    let mut body = body.as_slice();
+    let     json = read_string(&mut body)?;
+    let     json = serde_json::from_str::<StatusDeser>(&json).map_err(|err| invalid_data(format!("Malformed status response: {err}")))?;
+
+    let payload = rand::random::<i64>();
+    let sent_at = Instant::now();
+    write_packet(&mut stream, PACKET_PING, &payload.to_be_bytes()).await?;
+    let (packet_id, body) = read_packet(&mut stream).await?;
+    let latency = sent_at.elapsed();
+    if (packet_id != PACKET_PING || body.as_slice() != payload.to_be_bytes()) {
+        return Err(invalid_data("Server answered the ping with the wrong payload"));
+    }
+
+    Ok(json.into_status(latency))
+}
+
+
+#[derive(Deser)]
+struct StatusDeser {
+    version     : Option<StatusVersionDeser>,
+    players     : Option<StatusPlayersDeser>,
+    #[serde(default)]
+    description : serde_json::Value,
+    favicon     : Option<String>
+}
+
+#[derive(Deser)]
+struct StatusVersionDeser {
+    name     : String,
+    protocol : i32
+}
+
+#[derive(Deser)]
+struct StatusPlayersDeser {
+    online : u32,
+    max    : u32,
+    #[serde(default)]
+    sample : Vec<StatusPlayerDeser>
+}
+
+#[derive(Deser)]
+struct StatusPlayerDeser {
+    id   : String,
+    name : String
+}
+
+impl StatusDeser {
+    fn into_status(self, latency : Duration) -> ServerStatus {
+        let players = self.players.unwrap_or(StatusPlayersDeser { online : 0, max : 0, sample : Vec::new() });
+        ServerStatus {
+            version : self.version.map_or(ServerVersion { name : String::new(), protocol : 0 }, |version| ServerVersion { name : strip_formatting(&version.name), protocol : version.protocol }),
+            players : ServerPlayers {
+                online : players.online,
+                max    : players.max,
+                // Plugins often fill the sample with coloured text lines under a nil UUID, which are not players.
+                sample : players.sample.into_iter()
+                    .filter_map(|player| Some(ServerPlayer { id : Uuid::parse_str(&player.id).ok().filter(|id| ! id.is_nil())?, name : player.name }))
+                    .collect()
+            },
+            motd    : strip_formatting(&chat_text(&self.description)),
+            favicon : self.favicon.filter(|favicon| favicon.starts_with("data:image/png;base64,")),
+            latency
+        }
+    }
+}
+
+// The description is a chat component: a plain string, an object with `text` and `extra`, or an array of either.
+fn chat_text(component : &serde_json::Value) -> String {
+    match (component) {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(parts) => parts.iter().map(chat_text).collect(),
+        serde_json::Value::Object(map)  => {
+            let mut text = map.get("text").map(chat_text).unwrap_or_default();
+            if let Some(extra) = map.get("extra") {
+                text.push_str(&chat_text(extra));
+            }
+            text
+        },
+        _ => String::new()
+    }
+}
+
+fn strip_formatting(text : &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars    = text.chars();
+    while let Some(ch) = chars.next() {
+        if (ch == '\u{a7}') { chars.next(); }
+        else { stripped.push(ch); }
+    }
+    stripped.trim().to_string()
+}
+
+
+async fn write_packet(stream : &mut TcpStream, packet_id : i32, body : &[u8]) -> io::Result<()> {
+    let mut packet = Vec::with_capacity(body.len() + 10);
+    let mut inner  = Vec::with_capacity(body.len() + 5);
+    write_varint(&mut inner, packet_id);
+    inner.extend_from_slice(body);
+    write_varint(&mut packet, inner.len() as i32);
+    packet.extend_from_slice(&inner);
+    stream.write_all(&packet).await?;
+    stream.flush().await
+}
+
+async fn read_packet(stream : &mut TcpStream) -> io::Result<(i32, Vec<u8>)> {
+    let length = read_varint_async(stream).await?;
+    let length = usize::try_from(length).map_err(|_| invalid_data("Negative packet length"))?;
+    if (length == 0 || length > MAX_PACKET_LENGTH) {
+        return Err(invalid_data(format!("Packet length {length} is out of range")));
+    }
+    let mut packet = vec![0; length];
+    stream.read_exact(&mut packet).await?;
+    let mut body      = packet.as_slice();
+    let     packet_id = read_varint(&mut body)?;
+    Ok((packet_id, body.to_vec()))
+}
+
+fn write_varint(buf : &mut Vec<u8>, value : i32) {
+    let mut value = value as u32;
+    loop {
+        if (value & !0x7f == 0) {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+}
+
+fn read_varint(buf : &mut &[u8]) -> io::Result<i32> {
+    let mut value = 0u32;
+    for i in 0..5 {
+        let Some((&byte, rest)) = buf.split_first() else {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "Packet ended inside a VarInt"));
+        };
+        *buf = rest;
+        value |= ((byte & 0x7f) as u32) << (7 * i);
+        if (byte & 0x80 == 0) { return Ok(value as i32); }
+    }
+    Err(invalid_data("VarInt is longer than 5 bytes"))
+}
+
+async fn read_varint_async(stream : &mut TcpStream) -> io::Result<i32> {
+    let mut value = 0u32;
+    for i in 0..5 {
+        let mut byte = [0];
+        stream.read_exact(&mut byte).await?;
+        value |= ((byte[0] & 0x7f) as u32) << (7 * i);
+        if (byte[0] & 0x80 == 0) { return Ok(value as i32); }
+    }
+    Err(invalid_data("VarInt is longer than 5 bytes"))
+}
+
+fn write_string(buf : &mut Vec<u8>, value : &str) {
+    write_varint(buf, value.len() as i32);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+fn read_string(buf : &mut &[u8]) -> io::Result<String> {
+    let length = read_varint(buf)?;
+    let length = usize::try_from(length).map_err(|_| invalid_data("Negative string length"))?;
+    if (length > buf.len()) {
+        return Err(io::Error::new(ErrorKind::UnexpectedEof, "Packet ended inside a string"));
+    }
+    let (value, rest) = buf.split_at(length);
+    *buf = rest;
+    String::from_utf8(value.to_vec()).map_err(|_| invalid_data("String is not valid UTF-8"))
+}
+
+fn invalid_data(message : impl Into<String>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.into())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smol::net::TcpListener;
+
+    const STATUS_JSON : &str = r#"{
+        "version"     : { "name" : "§a1.21", "protocol" : 767 },
+        "players"     : { "online" : 2, "max" : 20, "sample" : [
+            { "id" : "069a79f4-44e9-4726-a5be-fca90e38aaf5", "name" : "Notch" },
+            { "id" : "00000000-0000-0000-0000-000000000000", "name" : "§cVisit our shop" }
+        ] },
+        "description" : { "text" : "§6Hello ", "extra" : [ { "text" : "world" } ] },
+        "favicon"     : "data:image/png;base64,AAAA"
+    }"#;
+
+    async fn listen() -> (TcpListener, ServerAddress) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port     = listener.local_addr().unwrap().port();
+        (listener, ServerAddress { host : "127.0.0.1".to_string(), port })
+    }
+
+    // Reads the handshake and status request, and answers with `STATUS_JSON`.
+    async fn answer_status(stream : &mut TcpStream, address : &ServerAddress) {
+        let (packet_id, handshake) = read_packet(stream).await.unwrap();
+        let mut handshake = handshake.as_slice();
+        assert_eq!(packet_id, PACKET_STATUS);
+        assert_eq!(read_varint(&mut handshake).unwrap(), STATUS_PROTOCOL_VERSION);
+        assert_eq!(read_string(&mut handshake).unwrap(), address.host);
+        assert_eq!(handshake[..2], address.port.to_be_bytes());
+        assert_eq!(read_varint(&mut &handshake[2..]).unwrap(), NEXT_STATE_STATUS);
+        assert_eq!(read_packet(stream).await.unwrap(), (PACKET_STATUS, Vec::new()));
+        let mut body = Vec::new();
+        write_string(&mut body, STATUS_JSON);
+        write_packet(stream, PACKET_STATUS, &body).await.unwrap();
+    }
+
+    #[test]
+    fn pings_modern_server() { smol::block_on(async {
+        let (listener, address) = listen().await;
+        let server_address = address.clone();
+        smol::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            answer_status(&mut stream, &server_address).await;
+            let (packet_id, payload) = read_packet(&mut stream).await.unwrap();
+            assert_eq!(packet_id, PACKET_PING);
+            write_packet(&mut stream, PACKET_PING, &payload).await.unwrap();
+        }).detach();
+
+        let status = ping(&address, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(status.version.name, "1.21");
+        assert_eq!(status.version.protocol, 767);
+        assert_eq!((status.players.online, status.players.max), (2, 20));
+        // The nil UUID entry is plugin text, not a player.
+        assert_eq!(status.players.sample.len(), 1);
+        assert_eq!(status.players.sample[0].name, "Notch");
+        assert_eq!(status.motd, "Hello world");
+        assert_eq!(status.favicon.as_deref(), Some("data:image/png;base64,AAAA"));
+    }) }
+
+    #[test]
+    fn falls_back_to_legacy_ping() { smol::block_on(async {
+        let (listener, address) = listen().await;
+        smol::spawn(async move {
+            // Servers from before 1.7 don't understand the handshake and hang up.
+            let (stream, _) = listener.accept().await.unwrap();
+            drop(stream);
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 3];
+            stream.read_exact(&mut request).await.unwrap();
+            assert_eq!(request, [ 0xfe, 0x01, 0xfa ]);
+            let kick = "\u{a7}1\u{0}78\u{0}\u{a7}e1.6.4\u{0}A \u{a7}lLegacy\u{a7}r server\u{0}3\u{0}20";
+            let kick = kick.encode_utf16().collect::<Vec<_>>();
+            let mut response = vec![ 0xff ];
+            response.extend_from_slice(&(kick.len() as u16).to_be_bytes());
+            for ch in kick {
+                response.extend_from_slice(&ch.to_be_bytes());
+            }
+            stream.write_all(&response).await.unwrap();
+        }).detach();
+
+        let status = ping(&address, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(status.version.name, "1.6.4");
+        assert_eq!(status.version.protocol, 78);
+        assert_eq!(status.motd, "A Legacy server");
+        assert_eq!((status.players.online, status.players.max), (3, 20));
+    }) }
+
+    #[test]
+    fn parses_pre_1_4_kick() {
+        let status = legacy::parse_kick("A server\u{a7}1\u{a7}10").unwrap();
+        assert_eq!((status.motd.as_str(), status.players.online, status.players.max), ("A server", 1, 10));
+        assert!(legacy::parse_kick("A server").is_none());
+    }
+
+    async fn status_length_error(length : &[u8]) -> io::Error {
+        let (listener, address) = listen().await;
+        let length = length.to_vec();
+        smol::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_packet(&mut stream).await.unwrap();
+            read_packet(&mut stream).await.unwrap();
+            stream.write_all(&length).await.unwrap();
+            stream.flush().await.unwrap();
+        }).detach();
+        ping_modern(&address).await.unwrap_err()
+    }
+
+    #[test]
+    fn rejects_bad_packet_lengths() { smol::block_on(async {
+        let mut oversized = Vec::new();
+        write_varint(&mut oversized, MAX_PACKET_LENGTH as i32 + 1);
+        let err = status_length_error(&oversized).await;
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("out of range"), "{err}");
+
+        let mut negative = Vec::new();
+        write_varint(&mut negative, -1);
+        let err = status_length_error(&negative).await;
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("Negative"), "{err}");
+
+        let err = status_length_error(&[ 0x80; 6 ]).await;
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("longer than 5 bytes"), "{err}");
+    }) }
+
+    #[test]
+    fn rejects_bad_string_lengths() {
+        let mut negative = Vec::new();
+        write_varint(&mut negative, -1);
+        assert_eq!(read_string(&mut negative.as_slice()).unwrap_err().kind(), ErrorKind::InvalidData);
+        let mut truncated = Vec::new();
+        write_varint(&mut truncated, 10);
+        truncated.extend_from_slice(b"short");
+        assert_eq!(read_string(&mut truncated.as_slice()).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn rejects_wrong_pong() { smol::block_on(async {
+        let (listener, address) = listen().await;
+        let server_address = address.clone();
+        smol::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            answer_status(&mut stream, &server_address).await;
+            let (_, mut payload) = read_packet(&mut stream).await.unwrap();
+            payload[7] ^= 1;
+            write_packet(&mut stream, PACKET_PING, &payload).await.unwrap();
+        }).detach();
+
+        let err = ping_modern(&address).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("wrong payload"), "{err}");
+    }) }
+
+    #[test]
+    fn parses_addresses() {
+        assert_eq!(ServerAddress::parse("play.example.com"), Some(ServerAddress { host : "play.example.com".to_string(), port : DEFAULT_PORT }));
+        assert_eq!(ServerAddress::parse("play.example.com:25566"), Some(ServerAddress { host : "play.example.com".to_string(), port : 25566 }));
+        assert_eq!(ServerAddress::parse("[::1]:25566"), Some(ServerAddress { host : "::1".to_string(), port : 25566 }));
+        assert_eq!(ServerAddress::parse("::1"), Some(ServerAddress { host : "::1".to_string(), port : DEFAULT_PORT }));
+        assert_eq!(ServerAddress::parse(":25565"), None);
+    }
+
+}
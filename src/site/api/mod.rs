@@ -0,0 +1,34 @@
+use crate::site::middleware::ContentSecurityPolicy;
+use tide::{
+    Body,
+    Response,
+    StatusCode
+};
+use serde::Serialize as Ser;
+
+
+pub mod profile;
+pub mod status;
+
+
+pub fn json_response(status : StatusCode, value : &impl Ser, cache_control : &str) -> tide::Result<Response> {
+    let mut res = Response::builder(status)
+        .body(Body::from_json(value)?)
+        .header("Cache-Control", cache_control)
+        .build();
+    // JSON is never rendered, so nothing may load from it if a browser is tricked into showing it.
+    res.insert_ext(ContentSecurityPolicy::default()
+        .directive("default-src", &[ "'none'" ])
+        .directive("script-src", &[])
+        .directive("style-src", &[])
+        .directive("font-src", &[])
+        .directive("img-src", &[])
+    );
+    Ok(res)
+}
+
+
+#[derive(Ser)]
+pub struct ApiError<'l> {
+    pub error : &'l str
+}
@@ -0,0 +1,134 @@
+use crate::{
+    auth::minecraft::account,
+    cache::profile::{ CachedProfile, BULK_LOOKUP_MAX },
+    skin::SkinModel,
+    site::{
+        SharedSiteState,
+        api::{ ApiError, json_response }
+    }
+};
+use std::sync::Arc;
+use tide::{
+    Request,
+    Response,
+    StatusCode
+};
+use surf::Client;
+use serde::Serialize as Ser;
+use uuid::Uuid;
+
+
+const NOT_FOUND_MAX_AGE : u64 = 60;
+
+
+#[derive(Ser)]
+struct ProfileJson<'l> {
+    id         : Uuid,
+    name       : &'l str,
+    skin_url   : Option<&'l str>,
+    skin_model : Option<SkinModel>,
+    cape_url   : Option<&'l str>
+}
+
+impl<'l> From<&'l CachedProfile> for ProfileJson<'l> {
+    fn from(profile : &'l CachedProfile) -> Self {
+        Self {
+            id         : profile.uuid,
+            name       : &profile.username,
+            skin_url   : profile.skin.as_ref().map(|skin| skin.url.as_str()),
+            skin_model : profile.skin.as_ref().map(|skin| skin.model),
+            cape_url   : profile.cape.as_ref().map(|cape| cape.url.as_str())
+        }
+    }
+}
+
+
+macro or_rate_limited($req:expr, $result:expr) {
+    match ($result) {
+        Err(err) if (err.status() == StatusCode::TooManyRequests) => {
+            let retry_after = ($req).state().profiles().retry_after().await.as_secs().max(1);
+            let mut res     = json_response(StatusCode::TooManyRequests, &ApiError { error : &err.to_string() }, "no-store")?;
+            res.insert_header("Retry-After", retry_after.to_string());
+            return Ok(res);
+        },
+        result => result?
+    }
+}
+
+
+fn profile_response(profile : Option<Arc<CachedProfile>>) -> tide::Result<Response> {
+    match (profile) {
+        Some(profile) => json_response(StatusCode::Ok, &ProfileJson::from(&*profile), &format!("public, max-age={}", profile.max_age().as_secs())),
+        None          => json_response(StatusCode::NotFound, &ApiError { error : "Profile not found" }, &format!("public, max-age={NOT_FOUND_MAX_AGE}"))
+    }
+}
+
+
+pub async fn route_by_uuid(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let uuid = Uuid::parse_str(req.param("uuid")?)
+        .map_err(|_| tide::Error::from_str(StatusCode::BadRequest, "Invalid UUID"))?;
+    let client = Client::new();
+    profile_response(or_rate_limited!(req, req.state().profiles().resolve(&client, uuid).await))
+}
+
+
+pub async fn route_by_name(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let username = req.param("name")?.to_string();
+    if (! account::is_valid_username(&username)) {
+        return Err(tide::Error::from_str(StatusCode::BadRequest, "Invalid username"));
+    }
+    let client = Client::new();
+    profile_response(or_rate_limited!(req, req.state().profiles().resolve_by_name(&client, &username).await))
+}
+
+
+pub async fn route_by_uuids(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let uuids = req.body_json::<Vec<Uuid>>().await
+        .map_err(|_| tide::Error::from_str(StatusCode::BadRequest, "Expected a JSON array of UUIDs"))?;
+    if (uuids.len() > BULK_LOOKUP_MAX) {
+        return Err(tide::Error::from_str(StatusCode::BadRequest, format!("At most {BULK_LOOKUP_MAX} UUIDs may be looked up at once")));
+    }
+    let client = Client::new();
+    let (resolved, result) = req.state().profiles().resolve_many(&client, &uuids).await;
+    batch_response(req, resolved, result).await
+}
+
+
+pub async fn route_by_names(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let usernames = req.body_json::<Vec<String>>().await
+        .map_err(|_| tide::Error::from_str(StatusCode::BadRequest, "Expected a JSON array of usernames"))?;
+    if (usernames.len() > BULK_LOOKUP_MAX) {
+        return Err(tide::Error::from_str(StatusCode::BadRequest, format!("At most {BULK_LOOKUP_MAX} usernames may be looked up at once")));
+    }
+    if (! usernames.iter().all(|username| account::is_valid_username(username))) {
+        return Err(tide::Error::from_str(StatusCode::BadRequest, "Invalid username"));
+    }
+    let client    = Client::new();
+    let usernames = usernames.iter().map(String::as_str).collect::<Vec<_>>();
+    let (resolved, result) = req.state().profiles().resolve_by_names(&client, &usernames).await;
+    batch_response(req, resolved, result).await
+}
+
+
+// A lookup cut off partway still returns what it found, but uncached so that clients retry for the rest.
+async fn batch_response(req : &Request<SharedSiteState>, profiles : Vec<Arc<CachedProfile>>, result : surf::Result<()>) -> tide::Result<Response> {
+    let body = profiles.iter().map(|profile| ProfileJson::from(&**profile)).collect::<Vec<_>>();
+    match (result) {
+        Ok(()) => {
+            let max_age = profiles.iter().map(|profile| profile.max_age().as_secs()).min().unwrap_or(NOT_FOUND_MAX_AGE);
+            json_response(StatusCode::Ok, &body, &format!("public, max-age={max_age}"))
+        },
+        Err(err) if (profiles.is_empty() && err.status() != StatusCode::TooManyRequests) => Err(err),
+        Err(err) => {
+            let mut res = if (profiles.is_empty()) {
+                json_response(StatusCode::TooManyRequests, &ApiError { error : &err.to_string() }, "no-store")?
+            } else {
+                json_response(StatusCode::Ok, &body, "no-store")?
+            };
+            if (err.status() == StatusCode::TooManyRequests) {
+                res.insert_header("Retry-After", req.state().profiles().retry_after().await.as_secs().max(1).to_string());
+            }
+            Ok(res)
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{
+    cache::status::CachedStatus,
+    site::{
+        SharedSiteState,
+        api::json_response
+    }
+};
+use tide::{
+    Request,
+    Response,
+    StatusCode
+};
+use serde::Serialize as Ser;
+use uuid::Uuid;
+
+
+#[derive(Ser)]
+struct StatusJson<'l> {
+    address    : String,
+    online     : bool,
+    checked_at : String,
+    last_seen  : Option<String>,
+    latency_ms : Option<u128>,
+    motd       : Option<&'l str>,
+    version    : Option<VersionJson<'l>>,
+    players    : Option<PlayersJson<'l>>,
+    favicon    : Option<&'l str>
+}
+
+#[derive(Ser)]
+struct VersionJson<'l> {
+    name     : &'l str,
+    protocol : i32
+}
+
+#[derive(Ser)]
+struct PlayersJson<'l> {
+    online : u32,
+    max    : u32,
+    sample : Vec<PlayerJson<'l>>
+}
+
+#[derive(Ser)]
+struct PlayerJson<'l> {
+    id   : Uuid,
+    name : &'l str
+}
+
+impl<'l> StatusJson<'l> {
+    fn new(address : String, cached : &'l CachedStatus) -> Self {
+        let online = cached.online();
+        Self {
+            address,
+            online     : online.is_some(),
+            checked_at : cached.checked_at.to_rfc3339(),
+            last_seen  : cached.last_seen.map(|last_seen| last_seen.to_rfc3339()),
+            latency_ms : online.map(|status| status.latency.as_millis()),
+            motd       : online.map(|status| status.motd.as_str()),
+            version    : online.map(|status| VersionJson { name : &status.version.name, protocol : status.version.protocol }),
+            players    : online.map(|status| PlayersJson {
+                online : status.players.online,
+                max    : status.players.max,
+                sample : status.players.sample.iter().map(|player| PlayerJson { id : player.id, name : &player.name }).collect()
+            }),
+            favicon    : online.and_then(|status| status.favicon.as_deref())
+        }
+    }
+}
+
+
+pub async fn route_status(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let status_cache = req.state().status();
+    let Some(address) = status_cache.address() else {
+        return Err(tide::Error::from_str(StatusCode::NotFound, "Server status is not configured"));
+    };
+    let Some(cached) = status_cache.current().await else {
+        return Err(tide::Error::from_str(StatusCode::ServiceUnavailable, "Server status has not been checked yet"));
+    };
+    json_response(StatusCode::Ok, &StatusJson::new(address.to_string(), &cached), &format!("public, max-age={}", cached.max_age().as_secs()))
+}
@@ -0,0 +1,157 @@
+use crate::{
+    layout::PreEscaped,
+    site::{ self, SharedSiteState },
+    util::{
+        compress::Encoding,
+        dotenv
+    }
+};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    sync::LazyLock
+};
+use tide::{
+    Request,
+    Response,
+    StatusCode,
+    http::{ Mime, mime }
+};
+use sha2::{ Digest, Sha256 };
+
+
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+const ASSETS_DIR               : &str = concat!(env!("CRATE_ROOT"), "/assets/");
+const IMMUTABLE_CACHE_CONTROL  : &str = "public, max-age=31536000, immutable";
+const REVALIDATE_CACHE_CONTROL : &str = "no-cache";
+
+
+pub struct EmbeddedAsset {
+    path   : &'static str,
+    bytes  : &'static [u8],
+    gzip   : Option<&'static [u8]>,
+    brotli : Option<&'static [u8]>
+}
+
+struct Asset {
+    embedded : &'static EmbeddedAsset,
+    hash     : String
+}
+
+impl Asset {
+
+    fn precompressed(&self, encoding : Encoding) -> Option<&'static [u8]> {
+        match (encoding) {
+            Encoding::Gzip   => self.embedded.gzip,
+            Encoding::Brotli => self.embedded.brotli
+        }
+    }
+
+}
+
+static ASSETS : LazyLock<HashMap<&'static str, Asset>> = LazyLock::new(||
+    EMBEDDED_ASSETS.iter().map(|embedded| (embedded.path, Asset { embedded, hash : hash(embedded.bytes) })).collect()
+);
+
+
+fn hash(bytes : &[u8]) -> String {
+    Sha256::digest(bytes)[..8].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn is_dev_mode() -> bool {
+    dotenv::try_var("ASSETS_DEV_MODE").is_some_and(|v| v.trim() == "true")
+}
+
+// In dev mode assets are read from the working tree, so only plain relative paths are accepted.
+fn is_safe_path(path : &str) -> bool {
+    path.split('/').all(|component| ! (component.is_empty() || component.starts_with('.') || component.contains('\\')))
+}
+
+fn read(path : &str) -> Option<Cow<'static, [u8]>> {
+    if (is_dev_mode()) {
+        if (! is_safe_path(path)) { return None; }
+        fs::read(format!("{ASSETS_DIR}{path}")).ok().map(Cow::Owned)
+    } else {
+        ASSETS.get(path).map(|asset| Cow::Borrowed(asset.embedded.bytes))
+    }
+}
+
+
+pub fn url(path : &str) -> String {
+    if (is_dev_mode()) {
+        return format!("/static/dev/{path}");
+    }
+    let asset = ASSETS.get(path).expect("unknown asset");
+    format!("/static/{}/{path}", asset.hash)
+}
+
+
+pub fn svg_icon(path : &str, class : Option<&str>) -> PreEscaped<String> {
+    let svg  = read(path).unwrap_or_default();
+    let svg  = String::from_utf8_lossy(&svg);
+    let root = svg.find("<svg").and_then(|start| Some(&svg[start..(start + svg[start..].find('>')?)])).unwrap_or_default();
+    let mut attributes = class.map_or_else(String::new, |class| format!(" class=\"{class}\""));
+    for name in [ "viewBox", "width", "height" ] {
+        if let Some(value) = root_attribute(root, name) {
+            attributes.push_str(&format!(" {name}=\"{value}\""));
+        }
+    }
+    // Referencing the file keeps `currentColor` working, which an `<img>` would not.
+    PreEscaped(format!("<svg{attributes}><use href=\"{}#icon\" width=\"100%\" height=\"100%\"/></svg>", url(path)))
+}
+
+fn root_attribute<'l>(root : &'l str, name : &str) -> Option<&'l str> {
+    let start = root.find(&format!(" {name}=\""))? + name.len() + 3;
+    let end   = start + root[start..].find('"')?;
+    Some(&root[start..end])
+}
+
+
+pub async fn route_static(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let not_found = || tide::Error::from_str(StatusCode::NotFound, StatusCode::NotFound.canonical_reason());
+    let url_hash  = req.param("hash")?;
+    let path      = req.param("path")?;
+
+    let (bytes, hash, cache_control, encoding) = if (is_dev_mode()) {
+        let bytes = read(path).ok_or_else(not_found)?;
+        let hash  = hash(&bytes);
+        (bytes, hash, REVALIDATE_CACHE_CONTROL, None)
+    } else {
+        let asset = ASSETS.get(path).ok_or_else(not_found)?;
+        // Pages rendered before a deploy still reference old hashes; they get the current asset, but not as immutable.
+        let cache_control = if (asset.hash == url_hash) { IMMUTABLE_CACHE_CONTROL } else { REVALIDATE_CACHE_CONTROL };
+        let precompressed = req.header("Accept-Encoding")
+            .and_then(|accept| Encoding::negotiate(accept.as_str()))
+            .and_then(|encoding| Some((encoding, asset.precompressed(encoding)?)));
+        match (precompressed) {
+            Some((encoding, bytes)) => (Cow::Borrowed(bytes), asset.hash.clone(), cache_control, Some(encoding)),
+            None                    => (Cow::Borrowed(asset.embedded.bytes), asset.hash.clone(), cache_control, None)
+        }
+    };
+
+    let etag = match (encoding) {
+        Some(encoding) => format!("\"{hash}-{}\"", encoding.as_str()),
+        None           => format!("\"{hash}\"")
+    };
+    if (site::etag_matches(req, &etag)) {
+        return Ok(Response::builder(StatusCode::NotModified)
+            .header("ETag", etag)
+            .header("Cache-Control", cache_control)
+            .build());
+    }
+    let content_type = path.rsplit_once('.')
+        .and_then(|(_, extension)| Mime::from_extension(extension))
+        .unwrap_or(mime::BYTE_STREAM);
+    let mut res = Response::builder(StatusCode::Ok)
+        .body(bytes.into_owned())
+        .content_type(content_type)
+        .header("ETag", etag)
+        .header("Cache-Control", cache_control)
+        .build();
+    if let Some(encoding) = encoding {
+        res.insert_header("Content-Encoding", encoding.as_str());
+    }
+    Ok(res)
+}
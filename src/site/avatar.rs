@@ -0,0 +1,103 @@
+use crate::{
+    site::{ SharedSiteState, png },
+    skin::{ self, CapeTexture, SkinTexture },
+    util::image
+};
+use std::sync::{ Arc, LazyLock };
+use tide::{
+    Request,
+    Response,
+    StatusCode
+};
+use surf::Client;
+use serde::Deserialize as Deser;
+use uuid::Uuid;
+use ::image::{
+    RgbaImage,
+    imageops::{ self, FilterType }
+};
+
+
+static STEVE_FACE : LazyLock<RgbaImage> = LazyLock::new(|| {
+    image::from_png_b64(include_str!(concat!(env!("CRATE_ROOT"), "/assets/skin/steve_face.png.b64"))).unwrap().to_rgba8()
+});
+
+const DEFAULT_SIZE : u32 = 64;
+const MAX_SIZE     : u32 = 512;
+
+
+#[derive(Deser)]
+struct AvatarQuery {
+    size : Option<u32>
+}
+
+
+// The skin hash in the query changes the URL whenever the skin does, so cached avatars are never stale.
+pub fn avatar_url(uuid : Uuid, skin : Option<&SkinTexture>, size : u32) -> String {
+    match (skin) {
+        Some(skin) => format!("/avatar/{uuid}.png?size={size}&v={}", skin.texture_id()),
+        None       => format!("/avatar/{uuid}.png?size={size}")
+    }
+}
+
+pub fn cape_url(uuid : Uuid, cape : &CapeTexture, size : u32) -> String {
+    format!("/cape/{uuid}.png?size={size}&v={}", cape.texture_id())
+}
+
+
+pub async fn route_avatar(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let uuid = req.param("file")?.strip_suffix(".png")
+        .and_then(|uuid| Uuid::parse_str(uuid).ok())
+        .ok_or_else(|| tide::Error::from_str(StatusCode::NotFound, StatusCode::NotFound.canonical_reason()))?;
+    let size = req.query::<AvatarQuery>()?.size.unwrap_or(DEFAULT_SIZE);
+    // The face is 8x8 texels, so other sizes would scale unevenly.
+    if (! (8..=MAX_SIZE).contains(&size) || size % 8 != 0) {
+        return Err(tide::Error::from_str(StatusCode::BadRequest, format!("Avatar size must be a multiple of 8 between 8 and {MAX_SIZE}")));
+    }
+
+    let client   = Client::new();
+    let profiles = Arc::clone(req.state().profiles());
+    let profile  = profiles.resolve_public(&client, uuid).await;
+    let face = profile.as_ref().and_then(|profile| profile.face.as_ref().map(|face| (profile.skin.as_ref(), face)));
+
+    let texture_id = face.and_then(|(skin, _)| Some(skin?.texture_id())).unwrap_or("steve");
+    let etag       = format!("\"{texture_id}-{size}\"");
+    if let Some(res) = png::not_modified(req, &etag) {
+        return Ok(res);
+    }
+
+    let face   = face.map_or(&*STEVE_FACE, |(_, face)| &**face);
+    let scaled = imageops::resize(face, size, size, FilterType::Nearest);
+    Ok(png::response(image::to_png(&scaled)?, &etag))
+}
+
+
+pub async fn route_cape(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let uuid = req.param("file")?.strip_suffix(".png")
+        .and_then(|uuid| Uuid::parse_str(uuid).ok())
+        .ok_or_else(|| tide::Error::from_str(StatusCode::NotFound, StatusCode::NotFound.canonical_reason()))?;
+    let size = req.query::<AvatarQuery>()?.size.unwrap_or(DEFAULT_SIZE);
+    if (! (16..=MAX_SIZE).contains(&size)) {
+        return Err(tide::Error::from_str(StatusCode::BadRequest, format!("Cape size must be between 16 and {MAX_SIZE}")));
+    }
+
+    let client   = Client::new();
+    let profiles = Arc::clone(req.state().profiles());
+    let profile  = profiles.resolve_public(&client, uuid).await;
+    let Some((profile, cape)) = profile.as_ref().and_then(|profile| Some((profile, profile.cape.as_ref()?))) else {
+        return Err(tide::Error::from_str(StatusCode::NotFound, "Player has no cape"));
+    };
+
+    let etag = format!("\"{}-{size}\"", cape.texture_id());
+    if let Some(res) = png::not_modified(req, &etag) {
+        return Ok(res);
+    }
+
+    let Some(cape_texture) = profiles.cape_texture(&client, profile).await? else {
+        return Err(tide::Error::from_str(StatusCode::NotFound, "Player has no cape"));
+    };
+    // The outer face of the cape is 10x16 texels; `size` is its height.
+    let front  = skin::cape_front(&cape_texture).map_err(|err| tide::Error::from_str(StatusCode::UnprocessableEntity, err.to_string()))?;
+    let scaled = imageops::resize(&front, size * 10 / 16, size, FilterType::Nearest);
+    Ok(png::response(image::to_png(&scaled)?, &etag))
+}
@@ -0,0 +1,11 @@
+use crate::util::dotenv;
+
+
+pub const GITHUB_URL    : &str = "https://github.com/PipeworkMC";
+pub const CONTACT_EMAIL : &str = "pipeworkmc@duck.com";
+
+
+// The Matrix space invite, such as `https://matrix.to/#/#pipeworkmc:matrix.org`. Links to it are hidden until it is set.
+pub fn matrix_url() -> Option<&'static str> {
+    dotenv::try_var("MATRIX_URL").map(str::trim).filter(|url| ! url.is_empty())
+}
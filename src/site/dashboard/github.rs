@@ -0,0 +1,31 @@
+use crate::{
+    i18n::t,
+    site::{
+        self,
+        SharedSiteState,
+        flash::{ self, FlashLevel }
+    }
+};
+use std::sync::Arc;
+use tide::{
+    Request,
+    Response
+};
+
+
+pub async fn route_unlink(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let login = Arc::clone(req.state()).lookup_login_session(req).await;
+    site::require_logged_in!(req, login);
+    let Some(minecraft_uuid) = site::session_minecraft_uuid(req) else {
+        return Ok(tide::Redirect::see_other(site::pages::DASHBOARD_LOGIN.path).into());
+    };
+
+    // Usually a second submit of the same form, so it isn't worth an error page.
+    let locale = site::locale(req);
+    match (Arc::clone(req.state()).unlink_github_account(minecraft_uuid).await?) {
+        Some(link) => flash::push(req, FlashLevel::Success, t!(locale, "flash-github-unlinked", account = link.user.login.as_str())),
+        None       => flash::push(req, FlashLevel::Warning, t!(locale, "flash-github-not-linked"))
+    }
+
+    Ok(tide::Redirect::see_other(site::pages::DASHBOARD.path).into())
+}
@@ -0,0 +1,39 @@
+use crate::{
+    i18n::{ Locale, t },
+    site::{
+        self,
+        SharedSiteState,
+        flash::{ self, FlashLevel }
+    }
+};
+use std::sync::Arc;
+use tide::{
+    Request,
+    Response
+};
+use serde::Deserialize as Deser;
+
+
+#[derive(Deser)]
+struct LocaleForm {
+    locale : String
+}
+
+
+pub async fn route_locale(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let login = Arc::clone(req.state()).lookup_login_session(req).await;
+    site::require_logged_in!(req, login);
+    let Some(minecraft_uuid) = site::session_minecraft_uuid(req) else {
+        return Ok(tide::Redirect::see_other(site::pages::DASHBOARD_LOGIN.path).into());
+    };
+
+    // Anything that isn't a known locale, including "auto", goes back to `Accept-Language` negotiation.
+    let form = req.body_form::<LocaleForm>().await?;
+    let preferred = Locale::from_id(&form.locale);
+    Arc::clone(req.state()).set_locale(minecraft_uuid, preferred).await?;
+    // Confirm in the language that was just picked, not the one this request was negotiated in.
+    let locale = preferred.unwrap_or_else(|| Locale::negotiate(req.header("Accept-Language").map_or("", |accept| accept.as_str())));
+    flash::push(req, FlashLevel::Success, t!(locale, "flash-settings-saved"));
+
+    Ok(tide::Redirect::see_other(site::pages::DASHBOARD.path).into())
+}
@@ -1,14 +1,132 @@
-use crate::site::{ self, SharedSiteState };
+use crate::{
+    auth::{ github::maintainer_org, provider::AuthProviderKind },
+    i18n::{ Locale, t },
+    layout::{
+        self,
+        Render,
+        component::{ Button, ButtonStyle, Card, Centre, Field, Form, IconRows, Input, SelectOption }
+    },
+    site::{ self, SharedSiteState, avatar, theme::Theme }
+};
 use std::sync::Arc;
-use tide::{ Request, Response, StatusCode };
+use tide::{ Request, Response };
 
 
 pub mod login;
+pub mod github;
+pub mod theme;
+pub mod locale;
 
 
 pub async fn route_index(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
     let login = Arc::clone(req.state()).lookup_login_session(req).await;
-    site::require_logged_in!(login);
+    site::require_logged_in!(req, login);
 
-    Err(tide::Error::from_str(StatusCode::InternalServerError, "Page under construction"))
+    let state          = Arc::clone(req.state());
+    let minecraft_uuid = site::session_minecraft_uuid(req);
+    let github_link    = match (minecraft_uuid) {
+        Some(minecraft_uuid) => state.lookup_github_link(minecraft_uuid).await,
+        None                 => None
+    };
+    let profile        = match (minecraft_uuid) {
+        Some(minecraft_uuid) => state.profiles().lookup(minecraft_uuid).await,
+        None                 => None
+    };
+    let theme          = match (minecraft_uuid) {
+        Some(minecraft_uuid) => state.lookup_theme(minecraft_uuid).await,
+        None                 => Theme::System
+    };
+    let preferred      = match (minecraft_uuid) {
+        Some(minecraft_uuid) => state.lookup_locale(minecraft_uuid).await,
+        None                 => None
+    };
+    let locale         = site::locale(req);
+    let csrf_field     = layout::csrf_field!(req);
+    let linked_count   = github_link.iter().count();
+
+    let mut linked_rows = Vec::new();
+    if let Some(github_link) = &github_link {
+        linked_rows.push(Form {
+            action : "/dashboard/github/unlink",
+            csrf   : csrf_field.clone(),
+            fields : Vec::new(),
+            submit : Button {
+                label : t!(locale, "dashboard-unlink", account = github_link.user.login.as_str()),
+                icon  : Some(layout::icon_svg!("brand/github.svg")),
+                style : ButtonStyle::Plain
+            },
+            inline : false
+        }.render());
+    }
+    linked_rows.extend(state.auth_providers()
+        .filter(|p| p.kind() == AuthProviderKind::Link)
+        .filter(|p| ! (p.id() == "github" && github_link.is_some()))
+        .map(|p| p.render_button(locale))
+    );
+
+    let theme_form = Form {
+        action : "/dashboard/theme",
+        csrf   : csrf_field.clone(),
+        fields : vec![ Field {
+            name  : "theme",
+            label : None,
+            input : Input::Select(Theme::ALL.into_iter().map(|option| SelectOption {
+                value    : option.id().to_string(),
+                label    : option.name(locale),
+                lang     : None,
+                selected : option == theme
+            }).collect()),
+            error : None
+        } ],
+        submit : Button::new(t!(locale, "dashboard-save"), ButtonStyle::Secondary),
+        inline : true
+    };
+
+    let mut locale_options = vec![ SelectOption {
+        value    : "auto".to_string(),
+        label    : t!(locale, "dashboard-language-automatic"),
+        lang     : None,
+        selected : preferred.is_none()
+    } ];
+    locale_options.extend(Locale::all().map(|option| SelectOption {
+        value    : option.id().to_string(),
+        label    : option.name(),
+        lang     : Some(option.id()),
+        selected : preferred == Some(option)
+    }));
+    let locale_form = Form {
+        action : "/dashboard/locale",
+        csrf   : csrf_field,
+        fields : vec![ Field { name : "locale", label : None, input : Input::Select(locale_options), error : None } ],
+        submit : Button::new(t!(locale, "dashboard-save"), ButtonStyle::Secondary),
+        inline : true
+    };
+
+    Ok(tide::Response::from(layout::default(req,
+        layout::PageType::Normal,
+        login.as_ref().map(|l| &**l),
+        layout::PageMeta::new(t!(locale, "dashboard"), t!(locale, "dashboard-overview")).robots(layout::Robots::NoIndex),
+        Centre(::maud::html!{
+            @if let Some(minecraft_uuid) = minecraft_uuid {
+                div .skin_renders {
+                    img src=(format!("/render/body/{minecraft_uuid}.png")) alt=(t!(locale, "dashboard-skin-front"));
+                    img src=(format!("/render/body/{minecraft_uuid}.png?side=back")) alt=(t!(locale, "dashboard-skin-back"));
+                }
+                @if let Some(cape) = profile.as_ref().and_then(|profile| profile.cape.as_ref()) {
+                    div .skin_cape {
+                        img src=(avatar::cape_url(minecraft_uuid, cape, 64)) alt=(t!(locale, "dashboard-cape"));
+                        span { (cape.alias.clone().unwrap_or_else(|| t!(locale, "dashboard-cape"))) }
+                    }
+                }
+            }
+            (Card { title : Some(t!(locale, "dashboard-linked-accounts", count = linked_count)), body : ::maud::html!{
+                (IconRows(linked_rows))
+                @if let Some(org) = github_link.as_ref().filter(|github_link| github_link.is_maintainer()).and(maintainer_org()) {
+                    p .github_maintainer { (t!(locale, "dashboard-github-maintainer", org = org)) }
+                }
+            } })
+            (Card { title : Some(t!(locale, "dashboard-theme")), body : theme_form })
+            (Card { title : Some(t!(locale, "dashboard-language")), body : locale_form })
+        })
+    ).await))
 }
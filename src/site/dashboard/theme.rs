@@ -0,0 +1,37 @@
+use crate::{
+    i18n::t,
+    site::{
+        self,
+        SharedSiteState,
+        flash::{ self, FlashLevel },
+        theme::Theme
+    }
+};
+use std::sync::Arc;
+use tide::{
+    Request,
+    Response
+};
+use serde::Deserialize as Deser;
+
+
+#[derive(Deser)]
+struct ThemeForm {
+    theme : Theme
+}
+
+
+pub async fn route_theme(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let login = Arc::clone(req.state()).lookup_login_session(req).await;
+    site::require_logged_in!(req, login);
+    let Some(minecraft_uuid) = site::session_minecraft_uuid(req) else {
+        return Ok(tide::Redirect::see_other(site::pages::DASHBOARD_LOGIN.path).into());
+    };
+
+    let form = req.body_form::<ThemeForm>().await?;
+    Arc::clone(req.state()).set_theme(minecraft_uuid, form.theme).await?;
+    let locale = site::locale(req);
+    flash::push(req, FlashLevel::Success, t!(locale, "flash-settings-saved"));
+
+    Ok(tide::Redirect::see_other(site::pages::DASHBOARD.path).into())
+}
@@ -0,0 +1,59 @@
+use crate::{
+    layout::component::ToastKind,
+    site::SharedSiteState
+};
+use tide::Request;
+use serde::Serialize as Ser;
+use serde::Deserialize as Deser;
+
+
+const SESSION_KEY : &str = "flash";
+
+
+#[derive(Ser, Deser, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum FlashLevel {
+    Info,
+    Success,
+    Warning,
+    Error
+}
+
+impl From<FlashLevel> for ToastKind {
+    fn from(level : FlashLevel) -> Self {
+        match (level) {
+            FlashLevel::Info    => ToastKind::Info,
+            FlashLevel::Success => ToastKind::Success,
+            FlashLevel::Warning => ToastKind::Warning,
+            FlashLevel::Error   => ToastKind::Error
+        }
+    }
+}
+
+
+#[derive(Ser, Deser, Clone, Debug)]
+pub struct Flash {
+    pub level   : FlashLevel,
+    pub message : String
+}
+
+
+// Queues a message for the next page rendered with `layout::default`, usually the target of a redirect.
+pub fn push(req : &mut Request<SharedSiteState>, level : FlashLevel, message : String) {
+    let session    = req.session_mut();
+    let mut queued = session.get::<Vec<Flash>>(SESSION_KEY).unwrap_or_default();
+    // A redirect loop would otherwise queue the same message over and over.
+    if (! queued.iter().any(|flash| flash.level == level && flash.message == message)) {
+        queued.push(Flash { level, message });
+    }
+    _ = session.insert(SESSION_KEY, queued);
+}
+
+pub fn take(req : &mut Request<SharedSiteState>) -> Vec<Flash> {
+    let session = req.session_mut();
+    let queued  = session.get::<Vec<Flash>>(SESSION_KEY);
+    if (queued.is_some()) {
+        session.remove(SESSION_KEY);
+    }
+    queued.unwrap_or_default()
+}
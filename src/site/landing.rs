@@ -0,0 +1,139 @@
+use crate::{
+    i18n::t,
+    layout::{
+        self,
+        component::{ ButtonLink, ButtonStyle, Card, IconLink, IconRows }
+    },
+    site::{ self, SharedSiteState, community, pages }
+};
+use std::sync::Arc;
+use tide::{ Request, Response };
+
+
+pub async fn route_landing(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let login  = Arc::clone(req.state()).lookup_login_session(req).await;
+    let locale = site::locale(req);
+
+    let call_to_action = if (login.is_some()) {
+        ButtonLink { href : pages::DASHBOARD.path, label : t!(locale, "landing-open-dashboard"), icon : pages::DASHBOARD.icon(), style : ButtonStyle::Primary }
+    } else {
+        ButtonLink { href : pages::DASHBOARD_LOGIN.path, label : t!(locale, "dashboard-log-in"), icon : pages::DASHBOARD_LOGIN.icon(), style : ButtonStyle::Primary }
+    };
+
+    let mut community_rows = vec![ layout::Render::render(&IconLink {
+        href     : community::GITHUB_URL,
+        icon     : layout::icon_svg!("brand/github.svg"),
+        label    : t!(locale, "landing-community-github"),
+        external : true
+    }) ];
+    if let Some(matrix_url) = community::matrix_url() {
+        community_rows.push(layout::Render::render(&IconLink {
+            href     : matrix_url,
+            icon     : layout::icon_svg!("brand/matrix.svg"),
+            label    : t!(locale, "landing-community-matrix"),
+            external : true
+        }));
+    }
+    let mailto = format!("mailto:{}", community::CONTACT_EMAIL);
+    community_rows.push(layout::Render::render(&IconLink {
+        href     : &mailto,
+        icon     : layout::icon_svg!("mail.svg"),
+        label    : t!(locale, "landing-community-email"),
+        external : false
+    }));
+
+    let status_cache   = Arc::clone(req.state().status());
+    let server_address = status_cache.address();
+    let cached_status  = status_cache.current().await;
+    let status_body    = ::maud::html!{
+        @match (cached_status.as_ref().map(|cached| (cached.online(), cached))) {
+            Some((Some(status), _)) => {
+                div #server_status .landing_status data-state="online" {
+                    @if let Some(favicon) = &status.favicon {
+                        img .landing_status_favicon src=(favicon) alt="";
+                    }
+                    span .landing_status_dot {}
+                    span { (t!(locale, "landing-status-online")) }
+                }
+                @if (! status.motd.is_empty()) {
+                    p .landing_status_motd { (status.motd) }
+                }
+                p .landing_status_detail {
+                    (t!(locale, "landing-status-players", online = status.players.online, max = status.players.max))
+                    @if (! status.version.name.is_empty()) {
+                        " \u{b7} " (status.version.name)
+                    }
+                    " \u{b7} " (t!(locale, "landing-status-latency", ms = status.latency.as_millis() as u64))
+                }
+                @if (! status.players.sample.is_empty()) {
+                    ul .landing_status_players {
+                        @for player in &status.players.sample {
+                            li { img src=(format!("/avatar/{}.png?size=16", player.id)) alt=""; (player.name) }
+                        }
+                    }
+                }
+            },
+            Some((None, cached)) => {
+                div #server_status .landing_status data-state="offline" {
+                    span .landing_status_dot {}
+                    span { (t!(locale, "landing-status-offline")) }
+                }
+                @if let Some(last_seen) = cached.last_seen {
+                    p .landing_status_detail { (t!(locale, "landing-status-last-seen", time = last_seen.format("%Y-%m-%d %H:%M UTC").to_string())) }
+                }
+            },
+            None => {
+                div #server_status .landing_status {
+                    span .landing_status_dot {}
+                    span { (t!(locale, "landing-status-unknown")) }
+                }
+            }
+        }
+        @if let Some(server_address) = server_address {
+            p .landing_status_address { (t!(locale, "landing-status-address")) " " code { (server_address) } }
+        }
+    };
+
+    Ok(tide::Response::from(layout::default(req,
+        layout::PageType::Normal,
+        login.as_ref().map(|l| &**l),
+        layout::PageMeta::new(t!(locale, "nav-home"), "PipeworkMC"),
+        ::maud::html!{
+            section .landing_hero {
+                (layout::icon_png!("brand/pipework.png"))
+                h1 { "PipeworkMC" }
+                p .landing_tagline { (t!(locale, "landing-tagline")) }
+                (call_to_action)
+            }
+            div .landing_sections {
+                (Card {
+                    title : Some(t!(locale, "landing-status")),
+                    body  : status_body
+                })
+                div .landing_features {
+                    (Card {
+                        title : Some(t!(locale, "landing-feature-dashboard")),
+                        body  : ::maud::html!{
+                            p { (t!(locale, "landing-feature-dashboard-body")) }
+                            (ButtonLink { href : pages::DASHBOARD.path, label : t!(locale, "landing-open-dashboard"), icon : None, style : ButtonStyle::Secondary })
+                        }
+                    })
+                    (Card {
+                        title : Some(t!(locale, "landing-feature-editor")),
+                        body  : ::maud::html!{
+                            p { (t!(locale, "landing-feature-editor-body")) }
+                            span .landing_soon { (t!(locale, "landing-coming-soon")) }
+                        }
+                    })
+                    (Card {
+                        title : Some(t!(locale, "landing-feature-community")),
+                        body  : ::maud::html!{
+                            p { (t!(locale, "landing-feature-community-body")) }
+                            (IconRows(community_rows))
+                        }
+                    })
+                }
+            }
+        }
+    ).await))
+}
@@ -0,0 +1,71 @@
+use crate::util::compress::Encoding;
+use tide::{
+    Middleware,
+    Next,
+    Request,
+    StatusCode,
+    http::Method,
+    utils::async_trait
+};
+
+
+const MIN_COMPRESS_BYTES : usize = 1024;
+
+const COMPRESSIBLE_TYPES : &[&str] = &[
+    "text/html",
+    "text/css",
+    "text/plain",
+    "text/javascript",
+    "application/javascript",
+    "application/json",
+    "application/xml",
+    "text/xml",
+    "image/svg+xml"
+];
+
+
+pub struct CompressionMiddleware;
+
+#[async_trait]
+impl<State : Clone + Send + Sync + 'static> Middleware<State> for CompressionMiddleware {
+    async fn handle(&self, req : Request<State>, next : Next<'_, State>) -> tide::Result {
+        let encoding = req.header("Accept-Encoding").and_then(|accept| Encoding::negotiate(accept.as_str()));
+        let is_head  = req.method() == Method::Head;
+        let mut res  = next.run(req).await;
+        res.append_header("Vary", "Accept-Encoding");
+        let Some(encoding) = encoding else { return Ok(res); };
+
+        if (is_head
+            || res.status() == StatusCode::NoContent
+            || res.status() == StatusCode::NotModified
+            || res.header("Content-Encoding").is_some()
+            || res.len().is_none_or(|len| len < MIN_COMPRESS_BYTES)
+            || ! res.content_type().is_some_and(|mime| COMPRESSIBLE_TYPES.contains(&mime.essence()))
+        ) {
+            return Ok(res);
+        }
+
+        // Setting a body also sets its content type, so the handler's one is restored afterwards.
+        let content_type       = res.content_type();
+        let body               = res.take_body().into_bytes().await?;
+        let (body, compressed) = smol::unblock(move || {
+            let compressed = encoding.compress(&body);
+            (body, compressed)
+        }).await;
+        match (compressed) {
+            Ok(compressed) => {
+                res.set_body(compressed);
+                res.insert_header("Content-Encoding", encoding.as_str());
+                // The representation changed, so a strong validator from the handler no longer applies.
+                if let Some(etag) = res.header("ETag").map(|etag| etag.as_str().to_string()) && (! etag.starts_with("W/")) {
+                    res.insert_header("ETag", format!("W/{etag}"));
+                }
+            },
+            Err(_) => { res.set_body(body); }
+        }
+        if let Some(content_type) = content_type {
+            res.set_content_type(content_type);
+        }
+        Ok(res)
+    }
+}
@@ -0,0 +1,312 @@
+use crate::util::dotenv;
+use std::sync::Arc;
+use tide::{
+    Middleware,
+    Next,
+    Request,
+    Response,
+    StatusCode,
+    http::Method,
+    utils::async_trait
+};
+
+
+#[derive(Clone)]
+pub enum CorsOrigin {
+    Any,
+    Exact(String),
+    // `https://*.example.com`; the `*` matches one or more subdomain labels.
+    Wildcard {
+        prefix : String,
+        suffix : String
+    }
+}
+
+impl CorsOrigin {
+
+    pub fn parse(origin : &str) -> Self {
+        let origin = origin.trim().trim_end_matches('/');
+        if (origin == "*") {
+            return Self::Any;
+        }
+        match (origin.split_once('*')) {
+            Some((prefix, suffix)) => Self::Wildcard { prefix : prefix.to_ascii_lowercase(), suffix : suffix.to_ascii_lowercase() },
+            None                   => Self::Exact(origin.to_ascii_lowercase())
+        }
+    }
+
+    fn matches(&self, origin : &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        match (self) {
+            Self::Any                        => true,
+            Self::Exact(exact)               => origin == *exact,
+            Self::Wildcard { prefix, suffix } => {
+                origin.len() > prefix.len() + suffix.len()
+                    && origin.starts_with(prefix.as_str())
+                    && origin.ends_with(suffix.as_str())
+                    && origin[prefix.len()..(origin.len() - suffix.len())].bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+            }
+        }
+    }
+
+}
+
+
+#[derive(Clone)]
+pub struct CorsConfig {
+    origins     : Vec<CorsOrigin>,
+    methods     : Vec<Method>,
+    headers     : Vec<String>,
+    credentials : bool,
+    max_age     : u32
+}
+
+impl CorsConfig {
+
+    pub fn new() -> Self {
+        Self { origins : Vec::new(), methods : vec![ Method::Get ], headers : Vec::new(), credentials : false, max_age : 600 }
+    }
+
+    // Reads a comma separated origin list, such as `https://launcher.pipeworkmc.net,https://*.pipeworkmc.net`.
+    pub fn origins_from_env(mut self, key : &str) -> Self {
+        if let Some(origins) = dotenv::try_var(key) {
+            self.origins.extend(origins.split(',').filter(|origin| ! origin.trim().is_empty()).map(CorsOrigin::parse));
+        }
+        self
+    }
+
+    pub fn origin(mut self, origin : &str) -> Self {
+        self.origins.push(CorsOrigin::parse(origin));
+        self
+    }
+
+    pub fn methods(mut self, methods : &[Method]) -> Self {
+        self.methods = methods.to_vec();
+        self
+    }
+
+    pub fn headers(mut self, headers : &[&str]) -> Self {
+        self.headers = headers.iter().map(|header| header.to_ascii_lowercase()).collect();
+        self
+    }
+
+    pub fn credentials(mut self, credentials : bool) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    pub fn max_age(mut self, seconds : u32) -> Self {
+        self.max_age = seconds;
+        self
+    }
+
+    fn allows_origin(&self, origin : &str) -> bool {
+        self.origins.iter().any(|allowed| allowed.matches(origin))
+    }
+
+    fn allows_headers(&self, requested : &str) -> bool {
+        requested.split(',')
+            .map(|header| header.trim().to_ascii_lowercase())
+            .filter(|header| ! header.is_empty())
+            .all(|header| self.headers.contains(&header))
+    }
+
+    // Credentialed responses may not use the `*` wildcard, so the origin is echoed instead.
+    fn allow_origin_value(&self, origin : &str) -> String {
+        if (! self.credentials && self.origins.iter().any(|allowed| matches!(allowed, CorsOrigin::Any))) {
+            "*".to_string()
+        } else {
+            origin.to_string()
+        }
+    }
+
+}
+
+
+// Attach to a route group with `Route::with`. The group also needs an `OPTIONS` endpoint, such as
+//  `route_options`, or the router rejects preflight requests before this middleware runs.
+#[derive(Clone)]
+pub struct CorsMiddleware {
+    config : Arc<CorsConfig>
+}
+
+impl CorsMiddleware {
+    pub fn new(config : CorsConfig) -> Self {
+        Self { config : Arc::new(config) }
+    }
+}
+
+#[async_trait]
+impl<State : Clone + Send + Sync + 'static> Middleware<State> for CorsMiddleware {
+    async fn handle(&self, req : Request<State>, next : Next<'_, State>) -> tide::Result {
+        let origin = req.header("Origin").map(|origin| origin.last().as_str().to_string());
+        let origin = origin.filter(|origin| self.config.allows_origin(origin));
+
+        if (req.method() == Method::Options) && let Some(requested_method) = req.header("Access-Control-Request-Method") {
+            let mut res = Response::new(StatusCode::NoContent);
+            res.append_header("Vary", "Origin, Access-Control-Request-Method, Access-Control-Request-Headers");
+            let requested_method = requested_method.last().as_str().parse::<Method>().ok();
+            let requested_headers = req.header("Access-Control-Request-Headers").map_or("", |headers| headers.last().as_str());
+            if let Some(origin) = origin
+                && requested_method.is_some_and(|method| self.config.methods.contains(&method))
+                && self.config.allows_headers(requested_headers)
+            {
+                res.insert_header("Access-Control-Allow-Origin", self.config.allow_origin_value(&origin));
+                res.insert_header("Access-Control-Allow-Methods", self.config.methods.iter().map(|method| method.to_string()).collect::<Vec<_>>().join(", "));
+                if (! self.config.headers.is_empty()) {
+                    res.insert_header("Access-Control-Allow-Headers", self.config.headers.join(", "));
+                }
+                if (self.config.credentials) {
+                    res.insert_header("Access-Control-Allow-Credentials", "true");
+                }
+                res.insert_header("Access-Control-Max-Age", self.config.max_age.to_string());
+            }
+            return Ok(res);
+        }
+
+        let mut res = next.run(req).await;
+        res.append_header("Vary", "Origin");
+        if let Some(origin) = origin {
+            res.insert_header("Access-Control-Allow-Origin", self.config.allow_origin_value(&origin));
+            if (self.config.credentials) {
+                res.insert_header("Access-Control-Allow-Credentials", "true");
+            }
+        }
+        Ok(res)
+    }
+}
+
+
+// Preflights are answered by `CorsMiddleware`; this only handles plain `OPTIONS` requests.
+pub async fn route_options<State : Clone + Send + Sync + 'static>(_ : Request<State>) -> tide::Result<Response> {
+    Ok(Response::new(StatusCode::NoContent))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tide::http;
+
+    fn app(config : CorsConfig) -> tide::Server<()> {
+        let mut app = tide::new();
+        let mut api = app.at("/api");
+        api.with(CorsMiddleware::new(config));
+        api.at("*").options(route_options);
+        api.at("/data").get(|_| async { Ok("data") });
+        app
+    }
+
+    async fn send(app : &tide::Server<()>, method : Method, origin : Option<&str>, headers : &[(&str, &str)]) -> http::Response {
+        let mut req = http::Request::new(method, "http://localhost/api/data");
+        if let Some(origin) = origin {
+            req.insert_header("Origin", origin);
+        }
+        for &(name, value) in headers {
+            req.insert_header(name, value);
+        }
+        app.respond(req).await.unwrap()
+    }
+
+    fn header<'l>(res : &'l http::Response, name : &str) -> Option<&'l str> {
+        res.header(name).map(|values| values.last().as_str())
+    }
+
+    #[test]
+    fn matches_exact_origins() {
+        let origin = CorsOrigin::parse("https://Launcher.pipeworkmc.net/");
+        assert!(origin.matches("https://launcher.pipeworkmc.net"));
+        assert!(origin.matches("HTTPS://LAUNCHER.PIPEWORKMC.NET"));
+        for denied in [ "http://launcher.pipeworkmc.net", "https://launcher.pipeworkmc.net:8443", "https://launcher.pipeworkmc.net.example.com", "https://pipeworkmc.net", "null" ] {
+            assert!(! origin.matches(denied), "{denied}");
+        }
+    }
+
+    #[test]
+    fn matches_wildcard_origins() {
+        let origin = CorsOrigin::parse("https://*.example.com");
+        for allowed in [ "https://a.example.com", "https://a.b.example.com", "https://A-1.Example.com" ] {
+            assert!(origin.matches(allowed), "{allowed}");
+        }
+        for denied in [
+            "https://example.com",
+            "https://.example.com",
+            "https://evilexample.com",
+            "https://a.example.com:8443",
+            "https://a.example.com.evil.org",
+            "http://a.example.com",
+            "https://a_b.example.com",
+            "https://evil.org/.example.com",
+            "https://user@a.example.com"
+        ] {
+            assert!(! origin.matches(denied), "{denied}");
+        }
+    }
+
+    #[test]
+    fn allows_listed_origins() { smol::block_on(async {
+        let app = app(CorsConfig::new().origin("https://launcher.pipeworkmc.net"));
+        let res = send(&app, Method::Get, Some("https://launcher.pipeworkmc.net"), &[]).await;
+        assert_eq!(header(&res, "Access-Control-Allow-Origin"), Some("https://launcher.pipeworkmc.net"));
+        assert_eq!(header(&res, "Access-Control-Allow-Credentials"), None);
+        assert_eq!(header(&res, "Vary"), Some("Origin"));
+    }) }
+
+    #[test]
+    fn denies_other_origins() { smol::block_on(async {
+        let app = app(CorsConfig::new().origin("https://launcher.pipeworkmc.net"));
+        for origin in [ Some("https://evil.example.com"), None ] {
+            let mut res = send(&app, Method::Get, origin, &[]).await;
+            // The request still goes through; only the browser is kept from reading it.
+            assert_eq!(res.status(), StatusCode::Ok);
+            assert_eq!(res.body_string().await.unwrap(), "data");
+            assert_eq!(header(&res, "Access-Control-Allow-Origin"), None);
+            assert_eq!(header(&res, "Vary"), Some("Origin"));
+        }
+    }) }
+
+    #[test]
+    fn echoes_origins_with_credentials() { smol::block_on(async {
+        let res = send(&app(CorsConfig::new().origin("*")), Method::Get, Some("https://a.example.com"), &[]).await;
+        assert_eq!(header(&res, "Access-Control-Allow-Origin"), Some("*"));
+        let res = send(&app(CorsConfig::new().origin("*").credentials(true)), Method::Get, Some("https://a.example.com"), &[]).await;
+        assert_eq!(header(&res, "Access-Control-Allow-Origin"), Some("https://a.example.com"));
+        assert_eq!(header(&res, "Access-Control-Allow-Credentials"), Some("true"));
+    }) }
+
+    #[test]
+    fn answers_preflight_requests() { smol::block_on(async {
+        let app = app(CorsConfig::new()
+            .origin("https://*.example.com")
+            .methods(&[ Method::Get, Method::Post ])
+            .headers(&[ "Content-Type" ])
+            .max_age(3600)
+        );
+        let res = send(&app, Method::Options, Some("https://tools.example.com"), &[
+            ("Access-Control-Request-Method", "POST"),
+            ("Access-Control-Request-Headers", "content-type")
+        ]).await;
+        assert_eq!(res.status(), StatusCode::NoContent);
+        assert_eq!(header(&res, "Access-Control-Allow-Origin"), Some("https://tools.example.com"));
+        assert_eq!(header(&res, "Access-Control-Allow-Methods"), Some("GET, POST"));
+        assert_eq!(header(&res, "Access-Control-Allow-Headers"), Some("content-type"));
+        assert_eq!(header(&res, "Access-Control-Max-Age"), Some("3600"));
+        assert_eq!(header(&res, "Vary"), Some("Origin, Access-Control-Request-Method, Access-Control-Request-Headers"));
+
+        for (origin, method, headers) in [
+            ("https://example.com", "POST", "content-type"),
+            ("https://tools.example.com", "DELETE", "content-type"),
+            ("https://tools.example.com", "POST", "content-type, authorization")
+        ] {
+            let res = send(&app, Method::Options, Some(origin), &[ ("Access-Control-Request-Method", method), ("Access-Control-Request-Headers", headers) ]).await;
+            assert_eq!(res.status(), StatusCode::NoContent);
+            assert_eq!(header(&res, "Access-Control-Allow-Origin"), None, "{origin} {method} {headers}");
+        }
+
+        // Without `Access-Control-Request-Method` it is a plain `OPTIONS` request.
+        let res = send(&app, Method::Options, Some("https://tools.example.com"), &[]).await;
+        assert_eq!(res.status(), StatusCode::NoContent);
+        assert_eq!(header(&res, "Access-Control-Allow-Methods"), None);
+        assert_eq!(header(&res, "Access-Control-Allow-Origin"), Some("https://tools.example.com"));
+    }) }
+}
@@ -0,0 +1,113 @@
+use crate::{
+    layout,
+    site::SharedSiteState,
+    util::rand::{ self, constant_time_eq }
+};
+use tide::{
+    Body,
+    Middleware,
+    Next,
+    Request,
+    StatusCode,
+    http::{ Method, mime },
+    utils::async_trait
+};
+use smol::io::AsyncReadExt;
+
+
+pub const CSRF_FIELD  : &str = "csrf-token";
+const CSRF_HEADER     : &str = "X-CSRF-Token";
+const CSRF_SESSION    : &str = "csrf-token";
+const MAX_FORM_BYTES  : u64  = 64 * 1024;
+
+
+// Minted the first time a page renders `csrf_field!`, so image and asset requests never start a session.
+pub fn csrf_token(req : &Request<SharedSiteState>) -> String {
+    if let Some(token) = req.session().get_raw(CSRF_SESSION) {
+        return token;
+    }
+    let token = rand::gen_token();
+    // Clones of a session share its data, so this still reaches the session middleware.
+    req.session().clone().insert_raw(CSRF_SESSION, token.clone());
+    token
+}
+
+
+pub struct CsrfMiddleware {
+    exempt : Vec<&'static str>
+}
+
+impl CsrfMiddleware {
+
+    pub fn new() -> Self {
+        Self { exempt : Vec::new() }
+    }
+
+    // For routes that don't authenticate with the session cookie.
+    pub fn exempt(mut self, prefix : &'static str) -> Self {
+        self.exempt.push(prefix);
+        self
+    }
+
+}
+
+#[async_trait]
+impl Middleware<SharedSiteState> for CsrfMiddleware {
+    async fn handle(&self, mut req : Request<SharedSiteState>, next : Next<'_, SharedSiteState>) -> tide::Result {
+        if (! matches!(req.method(), Method::Post | Method::Put | Method::Patch | Method::Delete)
+            || self.exempt.iter().any(|prefix| req.url().path().starts_with(prefix))
+        ) {
+            return Ok(next.run(req).await);
+        }
+
+        let checked = match (req.session().get_raw(CSRF_SESSION)) {
+            Some(token) => check_token(&mut req, &token).await,
+            None        => Err("Missing CSRF token")
+        };
+        if let Err(reason) = checked {
+            return Ok(layout::error_page(&mut req, StatusCode::Forbidden, format!("Failed to verify form: {reason}")).await);
+        }
+
+        Ok(next.run(req).await)
+    }
+}
+
+
+async fn check_token(req : &mut Request<SharedSiteState>, expected : &str) -> Result<(), &'static str> {
+    if let Some(header) = req.header(CSRF_HEADER) {
+        return if (constant_time_eq(header.last().as_str(), expected)) { Ok(()) } else { Err("Invalid CSRF token") };
+    }
+    if (req.content_type().is_none_or(|content_type| content_type.essence() != mime::FORM.essence())) {
+        return Err("Missing CSRF token");
+    }
+    if (req.len().is_some_and(|len| (len as u64) > MAX_FORM_BYTES)) {
+        return Err("Form is too large");
+    }
+
+    let mut bytes = Vec::new();
+    if (req.take_body().take(MAX_FORM_BYTES + 1).read_to_end(&mut bytes).await.is_err()) {
+        return Err("Failed to read form");
+    }
+    if ((bytes.len() as u64) > MAX_FORM_BYTES) {
+        return Err("Form is too large");
+    }
+    let token = form_field(&bytes, CSRF_FIELD);
+    // The route reads the form again, so the body is put back.
+    let mut body = Body::from_bytes(bytes);
+    body.set_mime(mime::FORM);
+    req.set_body(body);
+
+    match (token) {
+        Some(token) if (constant_time_eq(&token, expected)) => Ok(()),
+        Some(_)                                             => Err("Invalid CSRF token"),
+        None                                                => Err("Missing CSRF token")
+    }
+}
+
+fn form_field(form : &[u8], name : &str) -> Option<String> {
+    String::from_utf8_lossy(form).split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == name).then(|| urlencoding::decode(&value.replace('+', " ")).ok().map(|value| value.into_owned())).flatten()
+    })
+}
+
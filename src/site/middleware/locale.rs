@@ -0,0 +1,30 @@
+use crate::{
+    i18n::Locale,
+    site::{ self, SharedSiteState }
+};
+use tide::{
+    Middleware,
+    Next,
+    Request,
+    utils::async_trait
+};
+
+
+pub struct LocaleMiddleware;
+
+#[async_trait]
+impl Middleware<SharedSiteState> for LocaleMiddleware {
+    async fn handle(&self, mut req : Request<SharedSiteState>, next : Next<'_, SharedSiteState>) -> tide::Result {
+        let preferred = match (site::session_minecraft_uuid(&req)) {
+            Some(minecraft_uuid) => req.state().lookup_locale(minecraft_uuid).await,
+            None                 => None
+        };
+        let locale = preferred.unwrap_or_else(|| Locale::negotiate(req.header("Accept-Language").map_or("", |accept| accept.as_str())));
+        req.set_ext(locale);
+
+        let mut res = next.run(req).await;
+        res.insert_header("Content-Language", locale.id());
+        res.append_header("Vary", "Accept-Language");
+        Ok(res)
+    }
+}
@@ -0,0 +1,17 @@
+mod compression;
+pub use compression::CompressionMiddleware;
+
+mod cors;
+pub use cors::{ CorsConfig, CorsMiddleware, route_options };
+
+mod session;
+pub use session::SessionMiddleware;
+
+mod csrf;
+pub use csrf::{ CSRF_FIELD, CsrfMiddleware, csrf_token };
+
+mod locale;
+pub use locale::LocaleMiddleware;
+
+mod security;
+pub use security::{ ContentSecurityPolicy, CspNonce, SecurityHeadersMiddleware };
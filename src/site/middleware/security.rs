@@ -0,0 +1,135 @@
+use crate::util::rand;
+use tide::{
+    Middleware,
+    Next,
+    Request,
+    utils::async_trait
+};
+
+
+const NONCE_SOURCE       : &str = "'nonce'";
+const REFERRER_POLICY    : &str = "strict-origin-when-cross-origin";
+const PERMISSIONS_POLICY : &str = "accelerometer=(), camera=(), geolocation=(), gyroscope=(), microphone=(), payment=(), usb=(), interest-cohort=()";
+// `'nonce'` is replaced with the nonce of the request.
+const CONTENT_SECURITY_POLICY : &[(&str, &[&str])] = &[
+    ("default-src",     &[ "'self'" ]),
+    ("script-src",      &[ NONCE_SOURCE ]),
+    ("style-src",       &[ NONCE_SOURCE, "https://fonts.googleapis.com" ]),
+    ("font-src",        &[ "https://fonts.gstatic.com" ]),
+    ("img-src",         &[ "'self'", "data:" ]),
+    ("object-src",      &[ "'none'" ]),
+    ("base-uri",        &[ "'none'" ]),
+    ("form-action",     &[ "'self'" ]),
+    ("frame-ancestors", &[ "'none'" ])
+];
+
+
+#[derive(Clone)]
+pub struct CspNonce(pub String);
+
+
+// Routes that need a different policy insert one of these into their response with `Response::insert_ext`.
+//  It only holds the changes, which the middleware merges into `CONTENT_SECURITY_POLICY`.
+#[derive(Clone, Default)]
+pub struct ContentSecurityPolicy {
+    replaced : Vec<(&'static str, Vec<String>)>
+}
+
+impl ContentSecurityPolicy {
+
+    // Replaces the default sources of a directive. With no sources the directive is left out, so `default-src` applies.
+    pub fn directive(mut self, name : &'static str, sources : &[&str]) -> Self {
+        self.replaced.retain(|(directive, _)| *directive != name);
+        self.replaced.push((name, sources.iter().map(|source| source.to_string()).collect()));
+        self
+    }
+
+    fn header(&self, nonce : &str) -> String {
+        let defaults = CONTENT_SECURITY_POLICY.iter().map(|&(name, sources)| (name, sources.to_vec()))
+            .filter(|(name, _)| ! self.replaced.iter().any(|(directive, _)| directive == name));
+        let replaced = self.replaced.iter().map(|(name, sources)| (*name, sources.iter().map(String::as_str).collect()));
+        defaults.chain(replaced).filter(|(_, sources)| ! sources.is_empty()).map(|(name, sources)| {
+            let mut directive = name.to_string();
+            for source in sources {
+                directive.push(' ');
+                if (source == NONCE_SOURCE) {
+                    directive.push_str(&format!("'nonce-{nonce}'"));
+                } else {
+                    directive.push_str(source);
+                }
+            }
+            directive
+        }).collect::<Vec<_>>().join("; ")
+    }
+
+}
+
+
+pub struct SecurityHeadersMiddleware;
+
+#[async_trait]
+impl<State : Clone + Send + Sync + 'static> Middleware<State> for SecurityHeadersMiddleware {
+    async fn handle(&self, mut req : Request<State>, next : Next<'_, State>) -> tide::Result {
+        let nonce = rand::gen_nonce();
+        req.set_ext(CspNonce(nonce.clone()));
+
+        let mut res    = next.run(req).await;
+        let     policy = res.ext::<ContentSecurityPolicy>().cloned().unwrap_or_default();
+        res.insert_header("Content-Security-Policy", policy.header(&nonce));
+        res.insert_header("X-Content-Type-Options", "nosniff");
+        res.insert_header("Referrer-Policy", REFERRER_POLICY);
+        res.insert_header("Permissions-Policy", PERMISSIONS_POLICY);
+        // `frame-ancestors` supersedes this, but older browsers only understand `X-Frame-Options`.
+        res.insert_header("X-Frame-Options", "DENY");
+        Ok(res)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tide::{ Response, StatusCode, http };
+
+    async fn policy_of(app : &tide::Server<()>, path : &str) -> String {
+        let res : http::Response = app.respond(http::Request::get(format!("http://localhost{path}").as_str())).await.unwrap();
+        res.header("Content-Security-Policy").unwrap().last().as_str().to_string()
+    }
+
+    fn directive<'l>(policy : &'l str, name : &str) -> Option<&'l str> {
+        policy.split("; ").find(|directive| directive.split(' ').next() == Some(name))
+    }
+
+    #[test]
+    fn routes_override_the_default_policy() { smol::block_on(async {
+        let mut app = tide::new();
+        app.with(SecurityHeadersMiddleware);
+        app.at("/default").get(|_| async { Ok("") });
+        app.at("/textures").get(|_| async {
+            let mut res = Response::new(StatusCode::Ok);
+            res.insert_ext(ContentSecurityPolicy::default().directive("img-src", &[ "'self'", "data:", "https://textures.minecraft.net" ]));
+            Ok(res)
+        });
+        app.at("/strict").get(|_| async {
+            let mut res = Response::new(StatusCode::Ok);
+            res.insert_ext(ContentSecurityPolicy::default().directive("default-src", &[ "'none'" ]).directive("style-src", &[]).directive("worker-src", &[ "'none'" ]));
+            Ok(res)
+        });
+
+        let default = policy_of(&app, "/default").await;
+        assert_eq!(directive(&default, "img-src"), Some("img-src 'self' data:"));
+        assert!(directive(&default, "script-src").is_some_and(|script_src| script_src.starts_with("script-src 'nonce-")));
+
+        // Loosened: one more image source, everything else as before.
+        let textures = policy_of(&app, "/textures").await;
+        assert_eq!(directive(&textures, "img-src"), Some("img-src 'self' data: https://textures.minecraft.net"));
+        assert_eq!(directive(&textures, "font-src"), directive(&default, "font-src"));
+
+        // Tightened: `default-src` replaced, `style-src` dropped so it falls back, and a new directive added.
+        let strict = policy_of(&app, "/strict").await;
+        assert_eq!(directive(&strict, "default-src"), Some("default-src 'none'"));
+        assert_eq!(directive(&strict, "style-src"), None);
+        assert_eq!(directive(&strict, "img-src"), Some("img-src 'self' data:"));
+        assert!(strict.ends_with("; worker-src 'none'"));
+    }) }
+}
@@ -0,0 +1,303 @@
+use crate::{
+    site::SharedSiteState,
+    util::dotenv
+};
+use core::fmt;
+use std::{
+    sync::Arc,
+    time::{ Duration, SystemTime }
+};
+use tide::{
+    Middleware,
+    Next,
+    Request,
+    StatusCode,
+    http::cookies::{ Cookie, CookieJar, Key, SameSite },
+    sessions::{ CookieStore, MemoryStore, Session },
+    utils::async_trait
+};
+use async_session::SessionStore;
+use smol::Timer;
+
+
+const COOKIE_NAME             : &str     = "pipeworkmc";
+// `Key::derive_from` panics on anything shorter.
+const MIN_SECRET_BYTES        : usize    = 32;
+const DEFAULT_MAX_AGE         : Duration = Duration::from_hours(30 * 24);
+const CLEANUP_INTERVAL        : Duration = Duration::from_mins(10);
+
+
+// `Memory` sessions do not survive a restart.
+#[derive(Clone, Debug)]
+pub enum SiteSessionStore {
+    Cookie(CookieStore),
+    Memory(MemoryStore),
+    Database(DatabaseStore)
+}
+
+#[async_trait]
+impl SessionStore for SiteSessionStore {
+
+    async fn load_session(&self, cookie_value : String) -> async_session::Result<Option<Session>> {
+        match (self) {
+            Self::Cookie(store)   => store.load_session(cookie_value).await,
+            Self::Memory(store)   => store.load_session(cookie_value).await,
+            Self::Database(store) => store.load_session(cookie_value).await
+        }
+    }
+
+    async fn store_session(&self, session : Session) -> async_session::Result<Option<String>> {
+        match (self) {
+            Self::Cookie(store)   => store.store_session(session).await,
+            Self::Memory(store)   => store.store_session(session).await,
+            Self::Database(store) => store.store_session(session).await
+        }
+    }
+
+    async fn destroy_session(&self, session : Session) -> async_session::Result {
+        match (self) {
+            Self::Cookie(store)   => store.destroy_session(session).await,
+            Self::Memory(store)   => store.destroy_session(session).await,
+            Self::Database(store) => store.destroy_session(session).await
+        }
+    }
+
+    async fn clear_store(&self) -> async_session::Result {
+        match (self) {
+            Self::Cookie(store)   => store.clear_store().await,
+            Self::Memory(store)   => store.clear_store().await,
+            Self::Database(store) => store.clear_store().await
+        }
+    }
+
+}
+
+
+// Sessions in `PipeworkDb`, stored as JSON under the session id.
+#[derive(Clone)]
+pub struct DatabaseStore(SharedSiteState);
+
+impl DatabaseStore {
+    fn new(state : &SharedSiteState) -> Self {
+        if (! cfg!(feature = "account-db")) {
+            panic!("SESSION_STORE=database needs the account-db feature");
+        }
+        Self(Arc::clone(state))
+    }
+}
+
+impl fmt::Debug for DatabaseStore {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DatabaseStore")
+    }
+}
+
+#[async_trait]
+impl SessionStore for DatabaseStore {
+
+    async fn load_session(&self, cookie_value : String) -> async_session::Result<Option<Session>> {
+        let id   = Session::id_from_cookie_value(&cookie_value)?;
+        let data = self.0.load_web_session(&id).await.map_err(|err| async_session::Error::msg(format!("Failed to load session: {err}")))?;
+        Ok(match (data) {
+            Some(data) => serde_json::from_str::<Session>(&data)?.validate(),
+            None       => None
+        })
+    }
+
+    async fn store_session(&self, session : Session) -> async_session::Result<Option<String>> {
+        let data = serde_json::to_string(&session)?;
+        self.0.save_web_session(session.id(), &data, session.expiry().copied()).await
+            .map_err(|err| async_session::Error::msg(format!("Failed to store session: {err}")))?;
+        session.reset_data_changed();
+        Ok(session.into_cookie_value())
+    }
+
+    async fn destroy_session(&self, session : Session) -> async_session::Result {
+        self.0.delete_web_session(session.id()).await.map_err(|err| async_session::Error::msg(format!("Failed to destroy session: {err}")))
+    }
+
+    async fn clear_store(&self) -> async_session::Result {
+        self.0.clear_web_sessions().await.map_err(|err| async_session::Error::msg(format!("Failed to clear sessions: {err}")))
+    }
+
+}
+
+
+// tide's own only takes one secret, and decides `Secure` from the request scheme.
+pub struct SessionMiddleware {
+    store     : SiteSessionStore,
+    keys      : Vec<Key>,
+    domain    : Option<String>,
+    same_site : SameSite,
+    secure    : bool,
+    http_only : bool,
+    max_age   : Option<Duration>
+}
+
+impl SessionMiddleware {
+
+    // Cookies signed with one of `SESSION_PREVIOUS_SECRETS` are re-signed with `SESSION_SECRET`.
+    pub fn from_env(state : &SharedSiteState) -> Self {
+        let secrets = [ dotenv::var("SESSION_SECRET") ].into_iter()
+            .chain(dotenv::try_var("SESSION_PREVIOUS_SECRETS").into_iter().flat_map(|secrets| secrets.split(',')))
+            .map(str::trim)
+            .filter(|secret| ! secret.is_empty());
+        let mut keys = Vec::new();
+        for (i, secret) in secrets.enumerate() {
+            if (secret.len() < MIN_SECRET_BYTES) {
+                let name = if (i == 0) { "SESSION_SECRET" } else { "SESSION_PREVIOUS_SECRETS" };
+                panic!("{name} must be at least {MIN_SECRET_BYTES} bytes long");
+            }
+            keys.push(Key::derive_from(secret.as_bytes()));
+        }
+
+        let store = match (dotenv::try_var("SESSION_STORE").map(str::trim)) {
+            None | Some("cookie") => SiteSessionStore::Cookie(CookieStore::new()),
+            Some("memory")        => SiteSessionStore::Memory(MemoryStore::new()),
+            Some("database")      => SiteSessionStore::Database(DatabaseStore::new(state)),
+            Some(other)           => panic!("Invalid SESSION_STORE {other:?}, expected cookie, memory or database")
+        };
+        match (&store) {
+            SiteSessionStore::Memory(store)   => spawn_memory_cleanup(store.clone()),
+            SiteSessionStore::Database(store) => spawn_database_cleanup(store.clone()),
+            SiteSessionStore::Cookie(_)       => { }
+        }
+
+        // Development logins run over plain HTTP, where browsers drop `Secure` cookies.
+        #[cfg(feature = "dev-login")]
+        let insecure = crate::auth::dev::is_enabled();
+        #[cfg(not(feature = "dev-login"))]
+        let insecure = false;
+
+        let same_site = same_site_from_env();
+        let secure    = bool_from_env("SESSION_COOKIE_SECURE").unwrap_or(! insecure);
+        // Browsers drop `SameSite=None` cookies that are not `Secure`, which would silently log everyone out.
+        if (same_site == SameSite::None && ! secure) {
+            panic!("SESSION_SAME_SITE=none needs SESSION_COOKIE_SECURE=true");
+        }
+
+        Self {
+            store,
+            keys,
+            domain    : dotenv::try_var("SESSION_COOKIE_DOMAIN").map(|domain| domain.trim().to_string()).filter(|domain| ! domain.is_empty()),
+            same_site,
+            secure,
+            http_only : bool_from_env("SESSION_COOKIE_HTTP_ONLY").unwrap_or(true),
+            max_age   : match (dotenv::try_var("SESSION_MAX_AGE").map(|max_age| max_age.trim().parse::<u64>().expect("Invalid SESSION_MAX_AGE"))) {
+                None          => Some(DEFAULT_MAX_AGE),
+                Some(0)       => None,
+                Some(max_age) => Some(Duration::from_secs(max_age))
+            }
+        }
+    }
+
+    // Returns the unsigned value and whether it was signed with an old key.
+    fn verify_signature(&self, cookie_value : &str) -> Option<(String, bool)> {
+        self.keys.iter().enumerate().find_map(|(i, key)| {
+            let mut jar = CookieJar::new();
+            jar.add(Cookie::new(COOKIE_NAME, cookie_value.to_string()));
+            jar.signed(key).get(COOKIE_NAME).map(|cookie| (cookie.value().to_string(), i > 0))
+        })
+    }
+
+    fn build_cookie(&self, cookie_value : String) -> Cookie<'static> {
+        let mut cookie = Cookie::build(COOKIE_NAME, cookie_value)
+            .http_only(self.http_only)
+            .same_site(self.same_site)
+            .secure(self.secure)
+            .path("/")
+            .finish();
+        if let Some(max_age) = self.max_age {
+            cookie.set_expires(Some((SystemTime::now() + max_age).into()));
+        }
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        let mut jar = CookieJar::new();
+        jar.signed(&self.keys[0]).add(cookie);
+        jar.get(COOKIE_NAME).unwrap().clone()
+    }
+
+    async fn load_or_create(&self, cookie_value : Option<String>) -> Session {
+        let session = match (cookie_value) {
+            Some(cookie_value) => self.store.load_session(cookie_value).await.ok().flatten(),
+            None               => None
+        };
+        session.and_then(|session| session.validate()).unwrap_or_default()
+    }
+
+}
+
+#[async_trait]
+impl Middleware<SharedSiteState> for SessionMiddleware {
+    async fn handle(&self, mut req : Request<SharedSiteState>, next : Next<'_, SharedSiteState>) -> tide::Result {
+        let cookie   = req.cookie(COOKIE_NAME);
+        let verified = cookie.as_ref().and_then(|cookie| self.verify_signature(cookie.value()));
+        // Server-side stores only return a cookie value for new sessions, so a rotated one is re-signed as is.
+        let rotated  = verified.as_ref().filter(|(_, rotated)| *rotated).map(|(cookie_value, _)| cookie_value.clone());
+
+        let mut session = self.load_or_create(verified.map(|(cookie_value, _)| cookie_value)).await;
+        // The expiry is only pushed back once half of it has run out, so sessions aren't rewritten on every request.
+        let renew = self.max_age.filter(|&max_age| session.expires_in().is_none_or(|left| left < max_age / 2));
+        if let Some(max_age) = renew {
+            session.expire_in(max_age);
+        }
+        req.set_ext(session.clone());
+
+        let mut res = next.run(req).await;
+
+        if (session.is_destroyed()) {
+            _ = self.store.destroy_session(session).await;
+            if let Some(mut cookie) = cookie {
+                cookie.set_path("/");
+                res.remove_cookie(cookie);
+            }
+        // Empty sessions are never stored, so anonymous requests for images and assets get no cookie.
+        } else if (rotated.is_some() || session.data_changed() || (renew.is_some() && session.len() > 0)) {
+            let cookie_value = self.store.store_session(session).await.map_err(|err| {
+                tide::Error::from_str(StatusCode::InternalServerError, format!("Failed to store session: {err}"))
+            })?;
+            if let Some(cookie_value) = cookie_value.or(rotated) {
+                res.insert_cookie(self.build_cookie(cookie_value));
+            }
+        }
+        Ok(res)
+    }
+}
+
+
+fn spawn_memory_cleanup(store : MemoryStore) {
+    smol::spawn(async move {
+        loop {
+            Timer::after(CLEANUP_INTERVAL).await;
+            _ = store.cleanup().await;
+        }
+    }).detach();
+}
+
+fn spawn_database_cleanup(store : DatabaseStore) {
+    smol::spawn(async move {
+        loop {
+            Timer::after(CLEANUP_INTERVAL).await;
+            _ = store.0.delete_expired_web_sessions().await;
+        }
+    }).detach();
+}
+
+fn bool_from_env(key : &str) -> Option<bool> {
+    dotenv::try_var(key).map(|value| match (value.trim()) {
+        "true"  => true,
+        "false" => false,
+        other   => panic!("Invalid {key} {other:?}, expected true or false")
+    })
+}
+
+// `Lax` keeps the session through the top-level redirects back from OAuth providers, which `Strict` would drop.
+fn same_site_from_env() -> SameSite {
+    match (dotenv::try_var("SESSION_SAME_SITE").map(|same_site| same_site.trim().to_ascii_lowercase()).as_deref()) {
+        None | Some("lax") => SameSite::Lax,
+        Some("strict")     => SameSite::Strict,
+        Some("none")       => SameSite::None,
+        Some(other)        => panic!("Invalid SESSION_SAME_SITE {other:?}, expected strict, lax or none")
+    }
+}
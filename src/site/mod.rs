@@ -1,6 +1,18 @@
 use crate::{
-    auth,
-    util::rand
+    auth::{
+        self,
+        github::GithubLink,
+        provider::AuthProvider
+    },
+    cache::{
+        profile::{ CachedProfile, ProfileCache },
+        render::RenderCache,
+        status::StatusCache,
+        texture::TextureCache
+    },
+    i18n::Locale,
+    skin::{ CapeTexture, SkinTexture },
+    util::{ dotenv, rand }
 };
 use pipeworkmc_db::{ PipeworkDb, LoginSession };
 use std::{
@@ -9,46 +21,116 @@ use std::{
 };
 use tide::{
     Request,
-    Response,
     StatusCode
 };
 use smol::lock::RwLock;
 use uuid::Uuid;
+use ::image::RgbaImage;
+use chrono::{ DateTime, Utc };
 
 
+pub mod provider;
 pub mod dashboard;
+pub mod api;
+pub mod avatar;
+pub mod render;
+pub mod assets;
+pub mod middleware;
+pub mod theme;
+use theme::Theme;
+pub mod pages;
+pub mod flash;
+pub mod seo;
+pub mod landing;
+pub mod community;
+mod png;
 
 
 pub type SharedSiteState = Arc<SiteState>;
 
 pub struct SiteState {
-    microsoft_oauth_url : String,
+    auth_providers      : Vec<Box<dyn AuthProvider>>,
     db                  : PipeworkDb,
-    login_sessions      : RwLock<HashMap<Uuid, Arc<LoginSession>>>
+    login_sessions      : RwLock<HashMap<Uuid, Arc<LoginSession>>>,
+    profiles            : Arc<ProfileCache>,
+    renders             : RenderCache,
+    textures            : Arc<TextureCache>,
+    status              : Arc<StatusCache>,
+    github_links        : RwLock<HashMap<Uuid, Arc<GithubLink>>>,
+    themes              : RwLock<HashMap<Uuid, Theme>>,
+    locales             : RwLock<HashMap<Uuid, Option<Locale>>>
 }
 
 impl SiteState {
 
     pub fn new(db : PipeworkDb) -> SharedSiteState {
+        let textures = Arc::new(TextureCache::from_env());
         Arc::new(SiteState {
-            microsoft_oauth_url : auth::minecraft::login::build_microsoft_access_code_url(None),
+            auth_providers      : Self::enabled_auth_providers(),
             db,
-            login_sessions      : RwLock::new(HashMap::new())
+            login_sessions      : RwLock::new(HashMap::new()),
+            profiles            : Arc::new(ProfileCache::new(Arc::clone(&textures))),
+            renders             : RenderCache::new(),
+            textures,
+            status              : Arc::new(StatusCache::from_env()),
+            github_links        : RwLock::new(HashMap::new()),
+            themes              : RwLock::new(HashMap::new()),
+            locales             : RwLock::new(HashMap::new())
         })
     }
 
+    fn enabled_auth_providers() -> Vec<Box<dyn AuthProvider>> {
+        let mut providers = Vec::<Box<dyn AuthProvider>>::new();
+        providers.push(Box::new(auth::minecraft::provider::MicrosoftAuthProvider));
+        if (auth::github::is_enabled()) {
+            providers.push(Box::new(auth::github::GithubAuthProvider));
+        }
+        #[cfg(feature = "dev-login")]
+        if (auth::dev::is_enabled()) {
+            providers.push(Box::new(auth::dev::DevAuthProvider));
+        }
+        providers
+    }
+
+    pub fn auth_providers(&self) -> impl Iterator<Item = &dyn AuthProvider> {
+        self.auth_providers.iter().map(|p| &**p)
+    }
+
+    pub fn lookup_auth_provider(&self, id : &str) -> Option<&dyn AuthProvider> {
+        self.auth_providers().find(|p| p.id() == id)
+    }
+
+    pub fn profiles(&self) -> &Arc<ProfileCache> {
+        &self.profiles
+    }
+
+    pub fn renders(&self) -> &RenderCache {
+        &self.renders
+    }
+
+    pub fn textures(&self) -> &TextureCache {
+        &self.textures
+    }
+
+    pub fn status(&self) -> &Arc<StatusCache> {
+        &self.status
+    }
+
     pub async fn lookup_login_session(&self, req : &mut Request<SharedSiteState>) -> Option<Arc<LoginSession>> {
+        let minecraft_uuid = session_minecraft_uuid(req)?;
         let session        = req.session_mut();
-        let minecraft_uuid = Uuid::parse_str(&session.get_raw("minecraft-uuid")?).ok()?;
         let sessionkey     = session.get_raw("pipeworkmc-sessionkey")?;
         if let Some(entry) = self.login_sessions.read().await.get(&minecraft_uuid) {
             if (sessionkey == entry.sessionkey) {
+                self.profiles.mark_active(minecraft_uuid).await;
                 return Some(Arc::clone(entry));
             }
         }
         if let Some(entry) = self.db.lookup_login_session(minecraft_uuid).await.ok().flatten() {
             if (sessionkey == entry.sessionkey) {
                 let login = Arc::new(entry);
+                self.profiles.seed(minecraft_uuid, &login.minecraft_username).await;
+                self.profiles.mark_active(minecraft_uuid).await;
                 self.login_sessions.write().await.insert(minecraft_uuid, Arc::clone(&login));
                 return Some(login);
             }
@@ -62,7 +144,9 @@ impl SiteState {
         req                : &mut Request<SharedSiteState>,
         minecraft_uuid     : Uuid,
         minecraft_username : String,
-        minecraft_skin     : Option<String>
+        minecraft_skin     : Option<SkinTexture>,
+        minecraft_texture  : Option<RgbaImage>,
+        minecraft_cape     : Option<CapeTexture>
     ) {
         let sessionkey = rand::gen_token();
         {
@@ -70,31 +154,264 @@ impl SiteState {
             session.insert_raw("pipeworkmc-sessionkey", sessionkey.clone());
             session.insert_raw("minecraft-uuid", minecraft_uuid.to_string());
         }
+        self.profiles.insert(CachedProfile {
+            uuid       : minecraft_uuid,
+            username   : minecraft_username.clone(),
+            skin       : minecraft_skin,
+            face       : CachedProfile::face_of(minecraft_texture.as_ref()),
+            cape       : minecraft_cape,
+            fetched_at : Some(Utc::now())
+        }).await;
+        self.profiles.mark_active(minecraft_uuid).await;
         let login = Arc::new(LoginSession {
             sessionkey,
             minecraft_username,
-            minecraft_skin
+            // Avatars are served from `/avatar` now, so the face is no longer kept in the session.
+            minecraft_skin : None
         });
         self.login_sessions.write().await.insert(minecraft_uuid, Arc::clone(&login));
         _ = self.db.create_login_session(minecraft_uuid, &login).await;
     }
 
+    pub async fn lookup_github_link(&self, minecraft_uuid : Uuid) -> Option<Arc<GithubLink>> {
+        if let Some(link) = self.github_links.read().await.get(&minecraft_uuid) {
+            return Some(Arc::clone(link));
+        }
+        let link = Arc::new(self.load_github_link(minecraft_uuid).await?);
+        self.github_links.write().await.insert(minecraft_uuid, Arc::clone(&link));
+        Some(link)
+    }
+
+    pub async fn link_github_account(&self, minecraft_uuid : Uuid, link : GithubLink) -> tide::Result<()> {
+        let owner = self.lookup_github_link_owner(link.user.id).await.map_err(|err| {
+            tide::Error::from_str(StatusCode::InternalServerError, format!("Failed to link GitHub account: {err}"))
+        })?;
+        if (owner.is_some_and(|owner| owner != minecraft_uuid)) {
+            return Err(tide::Error::from_str(StatusCode::Conflict, format!("GitHub account {} is already linked to another Minecraft account", link.user.login)));
+        }
+        self.save_github_link(minecraft_uuid, &link).await.map_err(|err| {
+            tide::Error::from_str(StatusCode::InternalServerError, format!("Failed to link GitHub account: {err}"))
+        })?;
+        self.github_links.write().await.insert(minecraft_uuid, Arc::new(link));
+        Ok(())
+    }
+
+    pub async fn unlink_github_account(&self, minecraft_uuid : Uuid) -> tide::Result<Option<Arc<GithubLink>>> {
+        let Some(link) = self.lookup_github_link(minecraft_uuid).await else {
+            return Ok(None);
+        };
+        self.delete_github_link(minecraft_uuid).await.map_err(|err| {
+            tide::Error::from_str(StatusCode::InternalServerError, format!("Failed to unlink GitHub account: {err}"))
+        })?;
+        self.github_links.write().await.remove(&minecraft_uuid);
+        Ok(Some(link))
+    }
+
+    // Players without a saved theme are cached as `System` too, so every page doesn't go to the database.
+    pub async fn lookup_theme(&self, minecraft_uuid : Uuid) -> Theme {
+        if let Some(theme) = self.themes.read().await.get(&minecraft_uuid) {
+            return *theme;
+        }
+        let theme = self.load_theme(minecraft_uuid).await.unwrap_or_default();
+        self.themes.write().await.insert(minecraft_uuid, theme);
+        theme
+    }
+
+    pub async fn set_theme(&self, minecraft_uuid : Uuid, theme : Theme) -> tide::Result<()> {
+        let saved = if (theme == Theme::System) { None } else { Some(theme.id()) };
+        self.save_theme(minecraft_uuid, saved).await.map_err(|err| {
+            tide::Error::from_str(StatusCode::InternalServerError, format!("Failed to save theme: {err}"))
+        })?;
+        self.themes.write().await.insert(minecraft_uuid, theme);
+        Ok(())
+    }
+
+    // `None` is cached as well, for players who follow `Accept-Language`.
+    pub async fn lookup_locale(&self, minecraft_uuid : Uuid) -> Option<Locale> {
+        if let Some(locale) = self.locales.read().await.get(&minecraft_uuid) {
+            return *locale;
+        }
+        let locale = self.load_locale(minecraft_uuid).await;
+        self.locales.write().await.insert(minecraft_uuid, locale);
+        locale
+    }
+
+    pub async fn set_locale(&self, minecraft_uuid : Uuid, locale : Option<Locale>) -> tide::Result<()> {
+        self.save_locale(minecraft_uuid, locale).await.map_err(|err| {
+            tide::Error::from_str(StatusCode::InternalServerError, format!("Failed to save language: {err}"))
+        })?;
+        self.locales.write().await.insert(minecraft_uuid, locale);
+        Ok(())
+    }
+
+}
+
+
+// Account data is only persisted with `account-db`, which needs the account tables in `pipeworkmc-db`.
+//  Without it, GitHub linking is disabled and preferences only last until the server restarts.
+#[cfg(feature = "account-db")]
+impl SiteState {
+
+    async fn load_github_link(&self, minecraft_uuid : Uuid) -> Option<GithubLink> {
+        self.db.lookup_github_link(minecraft_uuid).await.ok().flatten().map(GithubLink::from)
+    }
+
+    async fn lookup_github_link_owner(&self, github_id : u64) -> Result<Option<Uuid>, String> {
+        self.db.lookup_github_link_owner(github_id).await.map_err(|err| err.to_string())
+    }
+
+    async fn save_github_link(&self, minecraft_uuid : Uuid, link : &GithubLink) -> Result<(), String> {
+        self.db.create_github_link(minecraft_uuid, &link.to_record()).await.map_err(|err| err.to_string())
+    }
+
+    async fn delete_github_link(&self, minecraft_uuid : Uuid) -> Result<(), String> {
+        self.db.delete_github_link(minecraft_uuid).await.map_err(|err| err.to_string())
+    }
+
+    async fn load_theme(&self, minecraft_uuid : Uuid) -> Option<Theme> {
+        self.db.lookup_theme(minecraft_uuid).await.ok().flatten().and_then(|theme| Theme::from_id(&theme))
+    }
+
+    async fn save_theme(&self, minecraft_uuid : Uuid, theme : Option<&str>) -> Result<(), String> {
+        self.db.set_theme(minecraft_uuid, theme).await.map_err(|err| err.to_string())
+    }
+
+    async fn load_locale(&self, minecraft_uuid : Uuid) -> Option<Locale> {
+        self.db.lookup_locale(minecraft_uuid).await.ok().flatten().and_then(|locale| Locale::from_id(&locale))
+    }
+
+    async fn save_locale(&self, minecraft_uuid : Uuid, locale : Option<Locale>) -> Result<(), String> {
+        self.db.set_locale(minecraft_uuid, locale.map(Locale::id)).await.map_err(|err| err.to_string())
+    }
+
+    async fn load_web_session(&self, id : &str) -> Result<Option<String>, String> {
+        self.db.lookup_web_session(id).await.map_err(|err| err.to_string())
+    }
+
+    async fn save_web_session(&self, id : &str, data : &str, expiry : Option<DateTime<Utc>>) -> Result<(), String> {
+        self.db.store_web_session(id, data, expiry).await.map_err(|err| err.to_string())
+    }
+
+    async fn delete_web_session(&self, id : &str) -> Result<(), String> {
+        self.db.delete_web_session(id).await.map_err(|err| err.to_string())
+    }
+
+    async fn clear_web_sessions(&self) -> Result<(), String> {
+        self.db.clear_web_sessions().await.map_err(|err| err.to_string())
+    }
+
+    async fn delete_expired_web_sessions(&self) -> Result<(), String> {
+        self.db.delete_expired_web_sessions().await.map_err(|err| err.to_string())
+    }
+
+}
+
+#[cfg(not(feature = "account-db"))]
+const NO_ACCOUNT_DB : &str = "Built without the account-db feature";
+
+#[cfg(not(feature = "account-db"))]
+impl SiteState {
+
+    async fn load_github_link(&self, _minecraft_uuid : Uuid) -> Option<GithubLink> {
+        None
+    }
+
+    async fn lookup_github_link_owner(&self, github_id : u64) -> Result<Option<Uuid>, String> {
+        Ok(self.github_links.read().await.iter().find(|(_, link)| link.user.id == github_id).map(|(minecraft_uuid, _)| *minecraft_uuid))
+    }
+
+    async fn save_github_link(&self, _minecraft_uuid : Uuid, _link : &GithubLink) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn delete_github_link(&self, _minecraft_uuid : Uuid) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn load_theme(&self, _minecraft_uuid : Uuid) -> Option<Theme> {
+        None
+    }
+
+    async fn save_theme(&self, _minecraft_uuid : Uuid, _theme : Option<&str>) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn load_locale(&self, _minecraft_uuid : Uuid) -> Option<Locale> {
+        None
+    }
+
+    async fn save_locale(&self, _minecraft_uuid : Uuid, _locale : Option<Locale>) -> Result<(), String> {
+        Ok(())
+    }
+
+    // `SESSION_STORE=database` is refused at startup, so these are never reached.
+    async fn load_web_session(&self, _id : &str) -> Result<Option<String>, String> {
+        Err(NO_ACCOUNT_DB.to_string())
+    }
+
+    async fn save_web_session(&self, _id : &str, _data : &str, _expiry : Option<DateTime<Utc>>) -> Result<(), String> {
+        Err(NO_ACCOUNT_DB.to_string())
+    }
+
+    async fn delete_web_session(&self, _id : &str) -> Result<(), String> {
+        Err(NO_ACCOUNT_DB.to_string())
+    }
+
+    async fn clear_web_sessions(&self) -> Result<(), String> {
+        Err(NO_ACCOUNT_DB.to_string())
+    }
+
+    async fn delete_expired_web_sessions(&self) -> Result<(), String> {
+        Err(NO_ACCOUNT_DB.to_string())
+    }
+
+}
+
+
+pub fn session_minecraft_uuid(req : &Request<SharedSiteState>) -> Option<Uuid> {
+    Uuid::parse_str(&req.session().get_raw("minecraft-uuid")?).ok()
 }
 
+// Behind a proxy the request URL is the internal address, so `SITE_URL` is preferred for links that leave the site.
+pub fn base_url(req : &Request<SharedSiteState>) -> String {
+    match (dotenv::try_var("SITE_URL")) {
+        Some(site_url) => site_url.trim().trim_end_matches('/').to_string(),
+        None           => req.url().origin().ascii_serialization()
+    }
+}
+
+// Set by `LocaleMiddleware`; requests that bypass it get the default catalog.
+pub fn locale(req : &Request<SharedSiteState>) -> Locale {
+    req.ext::<Locale>().copied().unwrap_or_default()
+}
+
+// Set by `SecurityHeadersMiddleware`; without it there is no nonce and inline blocks are left unmarked.
+pub fn csp_nonce(req : &Request<SharedSiteState>) -> Option<&str> {
+    req.ext::<middleware::CspNonce>().map(|nonce| nonce.0.as_str())
+}
 
-pub async fn route_todo(_ : &mut Request<SharedSiteState>) -> tide::Result<Response> {
-    Err(tide::Error::from_str(StatusCode::InternalServerError, "Page under construction"))
+// `If-None-Match` uses weak comparison, so tags weakened by compression still match.
+pub fn etag_matches(req : &Request<SharedSiteState>, etag : &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    req.header("If-None-Match").is_some_and(|tags| tags.iter().any(|tag| tag.as_str().split(',').any(|tag| {
+        let tag = tag.trim();
+        tag == "*" || tag.trim_start_matches("W/") == etag
+    })))
 }
 
 
-pub macro require_logged_out($login:expr) {
+pub macro require_logged_out($req:expr, $login:expr) {
     if (($login).is_some()) {
-        return Ok(tide::Redirect::see_other("/dashboard").into());
+        let locale = crate::site::locale($req);
+        crate::site::flash::push($req, crate::site::flash::FlashLevel::Info, crate::i18n::t!(locale, "flash-already-logged-in"));
+        return Ok(tide::Redirect::see_other(crate::site::pages::DASHBOARD.path).into());
     }
 }
 
-pub macro require_logged_in($login:expr) {
+pub macro require_logged_in($req:expr, $login:expr) {
     if (($login).is_none()) {
-        return Ok(tide::Redirect::see_other("/dashboard/login").into());
+        let locale = crate::site::locale($req);
+        crate::site::flash::push($req, crate::site::flash::FlashLevel::Info, crate::i18n::t!(locale, "flash-log-in-required"));
+        return Ok(tide::Redirect::see_other(crate::site::pages::DASHBOARD_LOGIN.path).into());
     }
 }
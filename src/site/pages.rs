@@ -0,0 +1,177 @@
+use crate::{
+    i18n::{ Locale, t },
+    layout::{ self, Markup, PreEscaped },
+    site::assets
+};
+use pipeworkmc_db::LoginSession;
+
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Anyone,
+    LoggedOut,
+    LoggedIn
+}
+
+impl Permission {
+    pub fn allows(self, login : Option<&LoginSession>) -> bool {
+        match (self) {
+            Permission::Anyone    => true,
+            Permission::LoggedOut => login.is_none(),
+            Permission::LoggedIn  => login.is_some()
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Header,
+    // Listed in the sidebar of the nearest `Header` ancestor.
+    Sidebar,
+    Hidden
+}
+
+
+pub struct Page {
+    pub path       : &'static str,
+    // Fluent key of the page title.
+    pub title      : &'static str,
+    pub icon       : Option<&'static str>,
+    pub permission : Permission,
+    pub parent     : Option<&'static Page>,
+    pub placement  : Placement
+}
+
+impl Page {
+
+    pub fn title(&self, locale : Locale) -> String {
+        locale.text(self.title, None)
+    }
+
+    pub fn icon(&self) -> Option<PreEscaped<String>> {
+        self.icon.map(|icon| assets::svg_icon(icon, None))
+    }
+
+    pub fn ancestors(&'static self) -> impl Iterator<Item = &'static Page> {
+        std::iter::successors(Some(self), |page| page.parent)
+    }
+
+    fn is(&self, other : &Page) -> bool {
+        self.path == other.path
+    }
+
+    fn section(&'static self) -> Option<&'static Page> {
+        self.ancestors().find(|page| page.placement == Placement::Header)
+    }
+
+}
+
+
+pub static HOME : Page = Page {
+    path       : "/",
+    title      : "nav-home",
+    icon       : Some(layout::asset_path!("icon/home.svg")),
+    permission : Permission::Anyone,
+    parent     : None,
+    placement  : Placement::Header
+};
+
+pub static DASHBOARD : Page = Page {
+    path       : "/dashboard",
+    title      : "dashboard",
+    icon       : Some(layout::asset_path!("icon/dashboard.svg")),
+    permission : Permission::LoggedIn,
+    parent     : Some(&HOME),
+    placement  : Placement::Header
+};
+
+pub static DASHBOARD_LOGIN : Page = Page {
+    path       : "/dashboard/login",
+    title      : "dashboard-log-in",
+    icon       : Some(layout::asset_path!("icon/account.svg")),
+    permission : Permission::LoggedOut,
+    parent     : Some(&HOME),
+    placement  : Placement::Header
+};
+
+// Every page that shows up in navigation, in display order.
+pub static PAGES : &[&Page] = &[
+    &HOME,
+    &DASHBOARD,
+    &DASHBOARD_LOGIN
+];
+
+
+pub fn current(path : &str) -> Option<&'static Page> {
+    let path = if (path.len() > 1) { path.trim_end_matches('/') } else { path };
+    PAGES.iter().copied().find(|page| page.path == path)
+}
+
+
+fn link(page : &Page, locale : Locale, current : Option<&Page>, class : &str) -> Markup {
+    let active = current.is_some_and(|current| current.is(page));
+    let class  = if (active) { format!("{class} nav_active") } else { class.to_string() };
+    ::maud::html!{
+        a class=(class) href=(page.path) aria-current=[active.then_some("page")] {
+            @if let Some(icon) = page.icon() { (icon) }
+            span { (page.title(locale)) }
+        }
+    }
+}
+
+pub fn render_header(locale : Locale, login : Option<&LoginSession>, current : Option<&'static Page>) -> Markup {
+    let section = current.and_then(Page::section);
+    ::maud::html!{
+        nav #nav aria-label=(t!(locale, "nav-main")) {
+            @for page in PAGES.iter().filter(|page| page.placement == Placement::Header && page.permission.allows(login)) {
+                (link(page, locale, section, "nav_link"))
+            }
+        }
+    }
+}
+
+// Empty unless the current section has pages of its own.
+pub fn render_sidebar(locale : Locale, login : Option<&LoginSession>, current : Option<&'static Page>) -> Option<Markup> {
+    let section  = current.and_then(Page::section)?;
+    let children = PAGES.iter()
+        .filter(|page| page.placement == Placement::Sidebar && page.parent.is_some_and(|parent| parent.is(section)) && page.permission.allows(login))
+        .collect::<Vec<_>>();
+    if (children.is_empty() || ! section.permission.allows(login)) {
+        return None;
+    }
+    Some(::maud::html!{
+        aside #sidebar {
+            nav aria-label=(section.title(locale)) {
+                (link(section, locale, current, "sidebar_link"))
+                @for page in children {
+                    (link(page, locale, current, "sidebar_link"))
+                }
+            }
+        }
+    })
+}
+
+pub fn render_breadcrumbs(locale : Locale, current : Option<&'static Page>) -> Option<Markup> {
+    let mut trail = current?.ancestors().collect::<Vec<_>>();
+    if (trail.len() < 2) {
+        return None;
+    }
+    trail.reverse();
+    let last = trail.len() - 1;
+    Some(::maud::html!{
+        nav #breadcrumbs aria-label=(t!(locale, "nav-breadcrumbs")) {
+            ol {
+                @for (i, page) in trail.into_iter().enumerate() {
+                    li {
+                        @if (i == last) {
+                            span aria-current="page" { (page.title(locale)) }
+                        } @else {
+                            a href=(page.path) { (page.title(locale)) }
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
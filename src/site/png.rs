@@ -0,0 +1,26 @@
+use crate::site::{ self, SharedSiteState };
+use tide::{
+    Request,
+    Response,
+    StatusCode
+};
+
+
+pub const CACHE_CONTROL : &str = "public, max-age=86400, stale-while-revalidate=604800";
+
+
+pub fn not_modified(req : &Request<SharedSiteState>, etag : &str) -> Option<Response> {
+    site::etag_matches(req, etag).then(|| Response::builder(StatusCode::NotModified)
+        .header("ETag", etag)
+        .header("Cache-Control", CACHE_CONTROL)
+        .build())
+}
+
+pub fn response(png : impl Into<tide::Body>, etag : &str) -> Response {
+    Response::builder(StatusCode::Ok)
+        .body(png)
+        .content_type("image/png")
+        .header("ETag", etag)
+        .header("Cache-Control", CACHE_CONTROL)
+        .build()
+}
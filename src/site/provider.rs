@@ -0,0 +1,91 @@
+use crate::{
+    auth::{
+        github::{ self, GithubMembershipState },
+        provider::{ AuthIdentity, AuthProviderKind }
+    },
+    i18n::t,
+    site::{
+        self,
+        SharedSiteState,
+        flash::{ self, FlashLevel }
+    }
+};
+use std::sync::Arc;
+use tide::{
+    Request,
+    Response,
+    StatusCode
+};
+
+
+macro lookup_provider($state:expr, $req:expr) {
+    match (($state).lookup_auth_provider(($req).param("provider")?)) {
+        Some(provider) => provider,
+        None           => { return Err(tide::Error::from_str(StatusCode::NotFound, StatusCode::NotFound.canonical_reason())); }
+    }
+}
+
+macro require_provider_kind($req:expr, $login:expr, $kind:expr) {
+    match ($kind) {
+        AuthProviderKind::Login => { site::require_logged_out!($req, $login); },
+        AuthProviderKind::Link  => { site::require_logged_in!($req, $login); }
+    }
+}
+
+
+pub async fn route_start(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let state    = Arc::clone(req.state());
+    let provider = lookup_provider!(state, req);
+    {
+        let login = Arc::clone(&state).lookup_login_session(req).await;
+        require_provider_kind!(req, login, provider.kind());
+    }
+    provider.start(req).await
+}
+
+
+pub async fn route_callback(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let state    = Arc::clone(req.state());
+    let provider = lookup_provider!(state, req);
+    {
+        let login = Arc::clone(&state).lookup_login_session(req).await;
+        require_provider_kind!(req, login, provider.kind());
+    }
+
+    let locale = site::locale(req);
+    match (provider.callback(req).await?) {
+
+        AuthIdentity::Minecraft { uuid, username, skin, texture, cape } => {
+            let message = t!(locale, "flash-logged-in", username = username.as_str());
+            Arc::clone(&state).create_login_session(req, uuid, username, skin, texture, cape).await;
+            flash::push(req, FlashLevel::Success, message);
+        },
+
+        AuthIdentity::Github(link) => {
+            let Some(minecraft_uuid) = site::session_minecraft_uuid(req) else {
+                return Ok(tide::Redirect::see_other(site::pages::DASHBOARD_LOGIN.path).into());
+            };
+            let message = t!(locale, "flash-github-linked", account = link.user.login.as_str());
+            let pending = link.maintainer == GithubMembershipState::Pending;
+            state.link_github_account(minecraft_uuid, link).await?;
+            flash::push(req, FlashLevel::Success, message);
+            if let Some(org) = github::maintainer_org() && pending {
+                flash::push(req, FlashLevel::Warning, t!(locale, "flash-github-invitation-pending", org = org));
+            }
+        }
+
+    }
+
+    Ok(tide::Redirect::see_other(site::pages::DASHBOARD.path).into())
+}
+
+
+// Redirect URIs registered with the providers before `/auth/:provider/callback` existed still point here.
+pub async fn route_legacy_callback(req : &mut Request<SharedSiteState>, provider : &str) -> tide::Result<Response> {
+    let mut url = format!("/auth/{provider}/callback");
+    if let Some(query) = req.url().query() {
+        url.push('?');
+        url.push_str(query);
+    }
+    Ok(tide::Redirect::temporary(url).into())
+}
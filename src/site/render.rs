@@ -0,0 +1,113 @@
+use crate::{
+    cache::render::RenderKey,
+    site::{ SharedSiteState, png },
+    skin::render::{ self, RenderKind, RenderOptions },
+    util::image
+};
+use std::sync::Arc;
+use tide::{
+    Request,
+    Response,
+    StatusCode
+};
+use surf::Client;
+use serde::Deserialize as Deser;
+use uuid::Uuid;
+
+
+const MIN_SIZE  : u32 = 16;
+const MAX_SIZE  : u32 = 512;
+const MAX_PITCH : i32 = 90;
+
+
+#[derive(Deser)]
+struct RenderQuery {
+    size    : Option<u32>,
+    yaw     : Option<i32>,
+    pitch   : Option<i32>,
+    overlay : Option<bool>,
+    cape    : Option<bool>,
+    side    : Option<RenderSide>
+}
+
+#[derive(Deser, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum RenderSide {
+    Front,
+    Back
+}
+
+
+pub async fn route_head(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    route_render(req, RenderKind::Head).await
+}
+
+pub async fn route_body(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    route_render(req, RenderKind::Body).await
+}
+
+
+async fn route_render(req : &mut Request<SharedSiteState>, kind : RenderKind) -> tide::Result<Response> {
+    let uuid = req.param("file")?.strip_suffix(".png")
+        .and_then(|uuid| Uuid::parse_str(uuid).ok())
+        .ok_or_else(|| tide::Error::from_str(StatusCode::NotFound, StatusCode::NotFound.canonical_reason()))?;
+    let query    = req.query::<RenderQuery>()?;
+    let defaults = kind.default_options();
+    let size     = query.size.unwrap_or(defaults.size);
+    if (! (MIN_SIZE..=MAX_SIZE).contains(&size)) {
+        return Err(tide::Error::from_str(StatusCode::BadRequest, format!("Render size must be between {MIN_SIZE} and {MAX_SIZE}")));
+    }
+    // Normalised before turning around, so extreme yaws can't overflow.
+    let mut yaw = query.yaw.unwrap_or(defaults.yaw as i32).rem_euclid(360);
+    if (query.side == Some(RenderSide::Back)) {
+        yaw = (yaw + 180) % 360;
+    }
+    let pitch   = query.pitch.unwrap_or(defaults.pitch as i32).clamp(-MAX_PITCH, MAX_PITCH);
+    let overlay = query.overlay.unwrap_or(defaults.overlay);
+    let cape    = query.cape.unwrap_or(defaults.cape);
+
+    let client   = Client::new();
+    let profiles = Arc::clone(req.state().profiles());
+    let profile  = profiles.resolve_public(&client, uuid).await;
+    let Some(profile) = profile else {
+        return Err(tide::Error::from_str(StatusCode::NotFound, "Failed to render skin: Player not found"));
+    };
+    let Some(skin) = profile.skin.as_ref() else {
+        return Err(tide::Error::from_str(StatusCode::NotFound, "Failed to render skin: Player has no skin"));
+    };
+    let cape = (cape && kind == RenderKind::Body && profile.cape.is_some());
+
+    let key = RenderKey {
+        texture_id : skin.texture_id().to_string(),
+        cape_id    : profile.cape.as_ref().filter(|_| cape).map(|cape| cape.texture_id().to_string()),
+        kind,
+        size,
+        yaw,
+        pitch,
+        overlay
+    };
+    let etag = key.etag();
+    if let Some(res) = png::not_modified(req, &etag) {
+        return Ok(res);
+    }
+
+    let renders = req.state().renders();
+    if let Some(cached) = renders.lookup(&key).await {
+        return Ok(png::response((*cached).clone(), &etag));
+    }
+
+    let Some(texture) = profiles.skin_texture(&client, &profile).await? else {
+        return Err(tide::Error::from_str(StatusCode::NotFound, "Failed to render skin: Player has no skin"));
+    };
+    let cape_texture = if (cape) { profiles.cape_texture(&client, &profile).await? } else { None };
+    let model        = skin.model;
+    let options      = RenderOptions { size, yaw : yaw as f32, pitch : pitch as f32, overlay, cape : cape_texture.is_some() };
+    let rendered     = smol::unblock(move || -> tide::Result<Vec<u8>> {
+        let rendered = render::render(&texture, model, cape_texture.as_ref(), kind, &options).map_err(|err| {
+            tide::Error::from_str(StatusCode::UnprocessableEntity, format!("Failed to render skin: {err}"))
+        })?;
+        Ok(image::to_png(&rendered)?)
+    }).await?;
+    let rendered = renders.insert(key, rendered).await;
+    Ok(png::response((*rendered).clone(), &etag))
+}
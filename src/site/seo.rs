@@ -0,0 +1,70 @@
+use crate::site::{
+    self,
+    SharedSiteState,
+    pages::{ self, Permission }
+};
+use tide::{
+    Request,
+    Response,
+    StatusCode,
+    http::mime
+};
+
+
+const CACHE_CONTROL : &str = "public, max-age=3600";
+
+// Paths that are never pages, on top of the registry's logged-in pages.
+const DISALLOWED : &[&str] = &[ "/auth/", "/api/" ];
+
+
+fn is_public(page : &pages::Page) -> bool {
+    page.permission != Permission::LoggedIn
+}
+
+
+pub async fn route_robots(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let private = pages::PAGES.iter().filter(|page| ! is_public(page)).map(|page| page.path).collect::<Vec<_>>();
+    let mut robots = String::from("User-agent: *\n");
+    // Public pages nested under a private one, such as the login page, need an explicit `Allow`.
+    for page in pages::PAGES.iter().filter(|page| is_public(page)) {
+        if (private.iter().any(|private| page.path.starts_with(private))) {
+            robots.push_str(&format!("Allow: {}\n", page.path));
+        }
+    }
+    for path in private.iter().chain(DISALLOWED) {
+        robots.push_str(&format!("Disallow: {path}\n"));
+    }
+    robots.push_str(&format!("\nSitemap: {}/sitemap.xml\n", site::base_url(req)));
+
+    Ok(Response::builder(StatusCode::Ok)
+        .body(robots)
+        .content_type(mime::PLAIN)
+        .header("Cache-Control", CACHE_CONTROL)
+        .build())
+}
+
+
+pub async fn route_sitemap(req : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    let base_url = site::base_url(req);
+    let mut sitemap = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for page in pages::PAGES.iter().filter(|page| is_public(page)) {
+        sitemap.push_str(&format!("  <url><loc>{}</loc></url>\n", escape_xml(&format!("{base_url}{}", page.path))));
+    }
+    sitemap.push_str("</urlset>\n");
+
+    Ok(Response::builder(StatusCode::Ok)
+        .body(sitemap)
+        .content_type(mime::XML)
+        .header("Cache-Control", CACHE_CONTROL)
+        .build())
+}
+
+fn escape_xml(text : &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+
+// Browsers ask for this regardless of the `<link rel="icon">` in the page head.
+pub async fn route_favicon(_ : &mut Request<SharedSiteState>) -> tide::Result<Response> {
+    Ok(tide::Redirect::permanent(site::assets::url("icon/brand/pipework.png")).into())
+}
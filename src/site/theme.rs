@@ -0,0 +1,55 @@
+use crate::i18n::{ Locale, t };
+use serde::Deserialize as Deser;
+
+
+#[derive(Deser, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark
+}
+
+impl Theme {
+
+    pub const ALL : [Theme; 3] = [Theme::System, Theme::Light, Theme::Dark];
+
+    #[cfg(feature = "account-db")]
+    pub fn from_id(id : &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|theme| theme.id() == id)
+    }
+
+    pub fn id(self) -> &'static str {
+        match (self) {
+            Theme::System => "system",
+            Theme::Light  => "light",
+            Theme::Dark   => "dark"
+        }
+    }
+
+    pub fn name(self, locale : Locale) -> String {
+        match (self) {
+            Theme::System => t!(locale, "dashboard-theme-system"),
+            Theme::Light  => t!(locale, "dashboard-theme-light"),
+            Theme::Dark   => t!(locale, "dashboard-theme-dark")
+        }
+    }
+
+    // The `data-theme` attribute on `<html>`; absent when following `prefers-color-scheme`.
+    pub fn attribute(self) -> Option<&'static str> {
+        match (self) {
+            Theme::System => None,
+            theme         => Some(theme.id())
+        }
+    }
+
+    pub fn color_scheme(self) -> &'static str {
+        match (self) {
+            Theme::System => "dark light",
+            Theme::Light  => "light",
+            Theme::Dark   => "dark"
+        }
+    }
+
+}
@@ -0,0 +1,223 @@
+use crate::util::math;
+use core::fmt;
+use serde::Serialize as Ser;
+use serde::Deserialize as Deser;
+use image::{ RgbaImage, imageops };
+
+
+pub mod render;
+
+
+const MAX_SKIN_WIDTH : u32 = 1024;
+const MAX_CAPE_WIDTH : u32 = 1024;
+
+
+#[derive(Deser, Ser, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum SkinModel {
+    #[default]
+    #[serde(rename = "CLASSIC", alias = "classic", alias = "default")]
+    Classic,
+    #[serde(rename = "SLIM", alias = "slim")]
+    Slim
+}
+
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SkinTexture {
+    pub url   : String,
+    pub model : SkinModel
+}
+
+impl SkinTexture {
+
+    pub fn texture_id(&self) -> &str {
+        texture_id(&self.url)
+    }
+
+}
+
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CapeTexture {
+    pub url   : String,
+    pub alias : Option<String>
+}
+
+impl CapeTexture {
+
+    pub fn texture_id(&self) -> &str {
+        texture_id(&self.url)
+    }
+
+}
+
+
+// Mojang texture URLs end with the texture hash.
+pub fn texture_id(url : &str) -> &str {
+    url.rsplit('/').next().unwrap_or(url)
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SkinLayout {
+    pub scale  : u32,
+    pub legacy : bool
+}
+
+impl SkinLayout {
+
+    pub fn from_dimensions(width : u32, height : u32) -> Result<Self, SkinError> {
+        if (width == 0 || width % 64 != 0 || width > MAX_SKIN_WIDTH) {
+            return Err(SkinError::Dimensions(width, height));
+        }
+        let scale = width / 64;
+        if (height == width) {
+            Ok(Self { scale, legacy : false })
+        } else if (height * 2 == width) {
+            Ok(Self { scale, legacy : true })
+        } else {
+            Err(SkinError::Dimensions(width, height))
+        }
+    }
+
+    #[inline]
+    pub fn scaled(&self, x : u32, y : u32) -> (u32, u32) {
+        (x * self.scale, y * self.scale)
+    }
+
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CapeLayout {
+    pub scale : u32
+}
+
+impl CapeLayout {
+
+    pub fn from_dimensions(width : u32, height : u32) -> Result<Self, SkinError> {
+        if (width == 0 || width % 64 != 0 || width > MAX_CAPE_WIDTH || height * 2 != width) {
+            return Err(SkinError::CapeDimensions(width, height));
+        }
+        Ok(Self { scale : width / 64 })
+    }
+
+}
+
+
+#[derive(Debug)]
+pub enum SkinError {
+    Dimensions(u32, u32),
+    CapeDimensions(u32, u32)
+}
+impl fmt::Display for SkinError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self) {
+            SkinError::Dimensions(width, height)     => write!(f, "Unsupported skin dimensions {width}x{height}"),
+            SkinError::CapeDimensions(width, height) => write!(f, "Unsupported cape dimensions {width}x{height}")
+        }
+    }
+}
+impl std::error::Error for SkinError { }
+
+
+pub fn face(skin : &RgbaImage) -> Result<RgbaImage, SkinError> {
+    let     layout           = SkinLayout::from_dimensions(skin.width(), skin.height())?;
+    let     size             = 8 * layout.scale;
+    let     (head_x, head_y) = layout.scaled(8, 8);
+    let     (hat_x, hat_y)   = layout.scaled(40, 8);
+    let     channel          = |c : u8| (c as f32) / (u8::MAX as f32);
+    let mut image_face       = RgbaImage::new(size, size);
+    for (x, y, px,) in image_face.enumerate_pixels_mut() {
+        let head = skin.get_pixel(head_x + x, head_y + y).0.map(channel);
+        let hat  = skin.get_pixel(hat_x + x, hat_y + y).0.map(channel);
+        px.0[0] = (math::lerp(head[0]*head[3], hat[0], hat[3]).clamp(0.0, 1.0) * (u8::MAX as f32)) as u8;
+        px.0[1] = (math::lerp(head[1]*head[3], hat[1], hat[3]).clamp(0.0, 1.0) * (u8::MAX as f32)) as u8;
+        px.0[2] = (math::lerp(head[2]*head[3], hat[2], hat[3]).clamp(0.0, 1.0) * (u8::MAX as f32)) as u8;
+        px.0[3] = u8::MAX;
+    }
+    Ok(image_face)
+}
+
+
+pub fn cape_front(cape : &RgbaImage) -> Result<RgbaImage, SkinError> {
+    let layout = CapeLayout::from_dimensions(cape.width(), cape.height())?;
+    Ok(imageops::crop_imm(cape, layout.scale, layout.scale, 10 * layout.scale, 16 * layout.scale).to_image())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::image;
+    use render::{ RenderKind, render };
+
+    fn fixture(name : &str) -> Vec<u8> {
+        std::fs::read(format!("{}/test-data/skin/{name}.png", env!("CRATE_ROOT"))).unwrap()
+    }
+
+    fn skin(name : &str) -> RgbaImage {
+        image::decode(fixture(name)).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn classic_skin() {
+        let skin = skin("classic");
+        assert_eq!(SkinLayout::from_dimensions(skin.width(), skin.height()).unwrap(), SkinLayout { scale : 1, legacy : false });
+        // The hat covers the top row of the fixture's face.
+        let face = face(&skin).unwrap();
+        assert_eq!((face.width(), face.height()), (8, 8));
+        assert_eq!(face.get_pixel(0, 0).0, [ 0, 0, 255, 255 ]);
+        assert_eq!(face.get_pixel(0, 1).0, [ 255, 0, 0, 255 ]);
+    }
+
+    #[test]
+    fn slim_skin() {
+        let skin    = skin("slim");
+        let options = RenderKind::Body.default_options();
+        assert_eq!(SkinLayout::from_dimensions(skin.width(), skin.height()).unwrap(), SkinLayout { scale : 1, legacy : false });
+        // Only the model tells a slim skin apart, and it changes the arm geometry.
+        let slim    = render(&skin, SkinModel::Slim, None, RenderKind::Body, &options).unwrap();
+        let classic = render(&skin, SkinModel::Classic, None, RenderKind::Body, &options).unwrap();
+        assert_ne!(slim, classic);
+    }
+
+    #[test]
+    fn skin_model_names() {
+        assert_eq!(serde_json::from_str::<SkinModel>("\"SLIM\"").unwrap(), SkinModel::Slim);
+        assert_eq!(serde_json::from_str::<SkinModel>("\"slim\"").unwrap(), SkinModel::Slim);
+        assert_eq!(serde_json::from_str::<SkinModel>("\"CLASSIC\"").unwrap(), SkinModel::Classic);
+        assert_eq!(serde_json::from_str::<SkinModel>("\"default\"").unwrap(), SkinModel::Classic);
+        assert!(serde_json::from_str::<SkinModel>("\"wide\"").is_err());
+    }
+
+    #[test]
+    fn legacy_skin() {
+        let skin = skin("legacy");
+        assert_eq!(SkinLayout::from_dimensions(skin.width(), skin.height()).unwrap(), SkinLayout { scale : 1, legacy : true });
+        assert_eq!(face(&skin).unwrap().get_pixel(0, 1).0, [ 255, 0, 0, 255 ]);
+        assert!(render(&skin, SkinModel::Classic, None, RenderKind::Body, &RenderKind::Body.default_options()).is_ok());
+    }
+
+    #[test]
+    fn hd_skin() {
+        let skin = skin("hd");
+        assert_eq!(SkinLayout::from_dimensions(skin.width(), skin.height()).unwrap(), SkinLayout { scale : 2, legacy : false });
+        let face = face(&skin).unwrap();
+        assert_eq!((face.width(), face.height()), (16, 16));
+        assert_eq!(face.get_pixel(15, 1).0, [ 0, 0, 255, 255 ]);
+        assert_eq!(face.get_pixel(15, 2).0, [ 255, 0, 0, 255 ]);
+    }
+
+    #[test]
+    fn malformed_skin() {
+        let skin = skin("malformed");
+        assert!(matches!(SkinLayout::from_dimensions(skin.width(), skin.height()), Err(SkinError::Dimensions(64, 48))));
+        assert!(face(&skin).is_err());
+        assert!(image::decode(fixture("truncated")).is_err());
+        assert!(SkinLayout::from_dimensions(0, 0).is_err());
+        assert!(SkinLayout::from_dimensions(96, 96).is_err());
+        assert!(SkinLayout::from_dimensions(2048, 2048).is_err());
+    }
+
+}
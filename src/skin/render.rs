@@ -0,0 +1,339 @@
+use crate::skin::{ CapeLayout, SkinError, SkinLayout, SkinModel };
+use std::borrow::Cow;
+use image::{
+    Rgba,
+    RgbaImage,
+    imageops::{ self, FilterType }
+};
+
+
+const MAX_RENDER_SCALE : u32 = 4;
+const MARGIN           : f32 = 0.05;
+const EDGE_TOLERANCE   : f32 = 0.01;
+const DEPTH_TOLERANCE  : f32 = 0.001;
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RenderKind {
+    Head,
+    Body
+}
+
+impl RenderKind {
+
+    pub fn default_options(self) -> RenderOptions {
+        match (self) {
+            RenderKind::Head => RenderOptions { size : 128, yaw : -45.0, pitch : 30.0, overlay : true, cape : false },
+            RenderKind::Body => RenderOptions { size : 256, yaw : -25.0, pitch : 10.0, overlay : true, cape : true  }
+        }
+    }
+
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RenderOptions {
+    pub size    : u32,
+    pub yaw     : f32,
+    pub pitch   : f32,
+    pub overlay : bool,
+    pub cape    : bool
+}
+
+
+#[derive(Clone, Copy)]
+enum Face {
+    Front,
+    Back,
+    Right,
+    Left,
+    Top,
+    Bottom
+}
+
+impl Face {
+
+    const ALL : [Face; 6] = [Face::Front, Face::Back, Face::Right, Face::Left, Face::Top, Face::Bottom];
+
+    fn normal(self) -> [f32; 3] {
+        match (self) {
+            Face::Front  => [  0.0,  0.0,  1.0 ],
+            Face::Back   => [  0.0,  0.0, -1.0 ],
+            Face::Right  => [ -1.0,  0.0,  0.0 ],
+            Face::Left   => [  1.0,  0.0,  0.0 ],
+            Face::Top    => [  0.0,  1.0,  0.0 ],
+            Face::Bottom => [  0.0, -1.0,  0.0 ]
+        }
+    }
+
+    fn shade(self) -> f32 {
+        match (self) {
+            Face::Top                => 1.0,
+            Face::Front | Face::Back => 0.85,
+            Face::Right | Face::Left => 0.7,
+            Face::Bottom             => 0.5
+        }
+    }
+
+    // Texture region of this face within a box texture at (u, v), in base texels.
+    fn region(self, (u, v) : (u32, u32), [w, h, d] : [u32; 3], mirror : bool) -> (u32, u32, u32, u32) {
+        let face = match (self, mirror) {
+            (Face::Right, true) => Face::Left,
+            (Face::Left,  true) => Face::Right,
+            (face,        _   ) => face
+        };
+        match (face) {
+            Face::Top    => (u + d,         v,     w, d),
+            Face::Bottom => (u + d + w,     v,     w, d),
+            Face::Right  => (u,             v + d, d, h),
+            Face::Front  => (u + d,         v + d, w, h),
+            Face::Left   => (u + d + w,     v + d, d, h),
+            Face::Back   => (u + d + w + d, v + d, w, h)
+        }
+    }
+
+    // Position on this face of a box spanning `min..max`, for `s` along the texture's u axis and `t` along its v axis.
+    fn point(self, min : [f32; 3], max : [f32; 3], s : f32, t : f32) -> [f32; 3] {
+        let lerp = |a : f32, b : f32, t : f32| a + (b - a) * t;
+        match (self) {
+            Face::Front  => [ lerp(min[0], max[0], s), lerp(max[1], min[1], t), max[2] ],
+            Face::Back   => [ lerp(max[0], min[0], s), lerp(max[1], min[1], t), min[2] ],
+            Face::Right  => [ min[0], lerp(max[1], min[1], t), lerp(min[2], max[2], s) ],
+            Face::Left   => [ max[0], lerp(max[1], min[1], t), lerp(max[2], min[2], s) ],
+            Face::Top    => [ lerp(min[0], max[0], s), max[1], lerp(min[2], max[2], t) ],
+            Face::Bottom => [ lerp(min[0], max[0], s), min[1], lerp(max[2], min[2], t) ]
+        }
+    }
+
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PartTexture {
+    Skin,
+    Cape
+}
+
+struct Part {
+    texture    : PartTexture,
+    min        : [f32; 3],
+    size       : [u32; 3],
+    uv         : (u32, u32),
+    overlay_uv : Option<(u32, u32)>,
+    inflate    : f32,
+    mirror     : bool,
+    // Turned 180 degrees around the vertical axis, so the texture's front faces backwards.
+    rotated    : bool
+}
+
+fn parts(kind : RenderKind, model : SkinModel, legacy : bool, cape : bool) -> Vec<Part> {
+    let head = Part { texture : PartTexture::Skin, min : [ -4.0, 24.0, -4.0 ], size : [ 8, 8, 8 ], uv : (0, 0), overlay_uv : Some((32, 0)), inflate : 0.5, mirror : false, rotated : false };
+    if (kind == RenderKind::Head) {
+        return vec![ Part { min : [ -4.0, -4.0, -4.0 ], ..head } ];
+    }
+    let arm_width = if (model == SkinModel::Slim) { 3 } else { 4 };
+    let limb      = |x : f32, y : f32, width : u32, uv, overlay_uv, mirror| Part {
+        texture    : PartTexture::Skin,
+        min        : [ x, y, -2.0 ],
+        size       : [ width, 12, 4 ],
+        uv,
+        overlay_uv : if (legacy) { None } else { overlay_uv },
+        inflate    : 0.25,
+        mirror,
+        rotated    : false
+    };
+    let mut parts = vec![
+        head,
+        limb(-4.0, 12.0, 8, (16, 16), Some((16, 32)), false),
+        limb(-4.0 - (arm_width as f32), 12.0, arm_width, (40, 16), Some((40, 32)), false),
+        if (legacy) { limb(4.0, 12.0, arm_width, (40, 16), None, true) }
+        else { limb(4.0, 12.0, arm_width, (32, 48), Some((48, 48)), false) },
+        limb(-4.0, 0.0, 4, (0, 16), Some((0, 32)), false),
+        if (legacy) { limb(0.0, 0.0, 4, (0, 16), None, true) }
+        else { limb(0.0, 0.0, 4, (16, 48), Some((0, 48)), false) }
+    ];
+    if (cape) {
+        parts.push(Part { texture : PartTexture::Cape, min : [ -5.0, 8.0, -3.0 ], size : [ 10, 16, 1 ], uv : (0, 0), overlay_uv : None, inflate : 0.0, mirror : false, rotated : true });
+    }
+    parts
+}
+
+
+struct Quad {
+    corners : [[f32; 3]; 4],
+    colour  : [f32; 4]
+}
+
+
+struct Camera {
+    yaw_sin   : f32,
+    yaw_cos   : f32,
+    pitch_sin : f32,
+    pitch_cos : f32
+}
+
+impl Camera {
+
+    fn new(options : &RenderOptions) -> Self {
+        let (yaw_sin,   yaw_cos  ) = options.yaw.to_radians().sin_cos();
+        let (pitch_sin, pitch_cos) = options.pitch.to_radians().sin_cos();
+        Self { yaw_sin, yaw_cos, pitch_sin, pitch_cos }
+    }
+
+    // Returns `[screen x, screen y, depth]`, where larger depths are closer to the viewer.
+    fn project(&self, [x, y, z] : [f32; 3]) -> [f32; 3] {
+        let x1 =  x * self.yaw_cos + z * self.yaw_sin;
+        let z1 = -x * self.yaw_sin + z * self.yaw_cos;
+        let y2 =  y * self.pitch_cos - z1 * self.pitch_sin;
+        let z2 =  y * self.pitch_sin + z1 * self.pitch_cos;
+        [ x1, -y2, z2 ]
+    }
+
+}
+
+
+// Downscales HD textures to at most `MAX_RENDER_SCALE`, returning the texture and its new scale.
+fn limit_scale(texture : &RgbaImage, scale : u32) -> (Cow<'_, RgbaImage>, u32) {
+    let limited = scale.min(MAX_RENDER_SCALE);
+    if (limited == scale) { return (Cow::Borrowed(texture), scale); }
+    let resized = imageops::resize(texture, texture.width() * limited / scale, texture.height() * limited / scale, FilterType::Nearest);
+    (Cow::Owned(resized), limited)
+}
+
+
+pub fn render(skin : &RgbaImage, model : SkinModel, cape : Option<&RgbaImage>, kind : RenderKind, options : &RenderOptions) -> Result<RgbaImage, SkinError> {
+    let layout         = SkinLayout::from_dimensions(skin.width(), skin.height())?;
+    let (skin, scale)  = limit_scale(skin, layout.scale);
+    let cape           = match (cape) {
+        Some(cape) if (options.cape) => Some(limit_scale(cape, CapeLayout::from_dimensions(cape.width(), cape.height())?.scale)),
+        _                            => None
+    };
+
+    let camera    = Camera::new(options);
+    let mut quads = Vec::new();
+    for part in parts(kind, model, layout.legacy, cape.is_some()) {
+        let (texture, scale) = match (part.texture) {
+            PartTexture::Skin => (&*skin, scale),
+            PartTexture::Cape => match (&cape) {
+                Some((cape, cape_scale)) => (&**cape, *cape_scale),
+                None                     => continue
+            }
+        };
+        let rotate = |[x, y, z] : [f32; 3], min : [f32; 3], max : [f32; 3]| {
+            if (part.rotated) { [ min[0] + max[0] - x, y, min[2] + max[2] - z ] } else { [ x, y, z ] }
+        };
+        let mut layers = vec![ (part.uv, 0.0, true) ];
+        if (options.overlay) && let Some(overlay_uv) = part.overlay_uv {
+            layers.push((overlay_uv, part.inflate, false));
+        }
+        for (uv, inflate, opaque) in layers {
+            let min = [ part.min[0] - inflate, part.min[1] - inflate, part.min[2] - inflate ];
+            let max = [
+                part.min[0] + (part.size[0] as f32) + inflate,
+                part.min[1] + (part.size[1] as f32) + inflate,
+                part.min[2] + (part.size[2] as f32) + inflate
+            ];
+            for face in Face::ALL {
+                let [nx, ny, nz] = face.normal();
+                let normal       = if (part.rotated) { [ -nx, ny, -nz ] } else { [ nx, ny, nz ] };
+                if (camera.project(normal)[2] <= 0.0) { continue; }
+                let (ru, rv, rw, rh) = face.region(uv, part.size, part.mirror);
+                let (rw, rh)         = (rw * scale, rh * scale);
+                for tj in 0..rh {
+                    for ti in 0..rw {
+                        let tu = ru * scale + if (part.mirror) { rw - 1 - ti } else { ti };
+                        let tv = rv * scale + tj;
+                        let Some(texel) = texture.get_pixel_checked(tu, tv) else { continue; };
+                        let alpha = if (opaque) { 1.0 } else { (texel.0[3] as f32) / (u8::MAX as f32) };
+                        if (alpha <= 0.0) { continue; }
+                        let shade  = face.shade();
+                        let colour = [
+                            (texel.0[0] as f32) / (u8::MAX as f32) * shade,
+                            (texel.0[1] as f32) / (u8::MAX as f32) * shade,
+                            (texel.0[2] as f32) / (u8::MAX as f32) * shade,
+                            alpha
+                        ];
+                        let (s0, s1) = ((ti as f32) / (rw as f32), ((ti + 1) as f32) / (rw as f32));
+                        let (t0, t1) = ((tj as f32) / (rh as f32), ((tj + 1) as f32) / (rh as f32));
+                        let corners  = [ (s0, t0), (s1, t0), (s1, t1), (s0, t1) ]
+                            .map(|(s, t)| camera.project(rotate(face.point(min, max, s, t), min, max)));
+                        quads.push(Quad { corners, colour });
+                    }
+                }
+            }
+        }
+    }
+
+    let mut canvas = RgbaImage::new(options.size, options.size);
+    if (quads.is_empty()) { return Ok(canvas); }
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for [x, y, _] in quads.iter().flat_map(|quad| quad.corners) {
+        min_x = min_x.min(x); min_y = min_y.min(y);
+        max_x = max_x.max(x); max_y = max_y.max(y);
+    }
+    let size     = options.size as f32;
+    let fit      = (size * (1.0 - MARGIN * 2.0)) / (max_x - min_x).max(max_y - min_y);
+    let offset_x = (size - (max_x - min_x) * fit) / 2.0 - min_x * fit;
+    let offset_y = (size - (max_y - min_y) * fit) / 2.0 - min_y * fit;
+    for quad in &mut quads {
+        quad.corners = quad.corners.map(|[x, y, z]| [ x * fit + offset_x, y * fit + offset_y, z ]);
+    }
+
+    // Opaque texels are depth tested and written in any order, then translucent texels are blended back to front.
+    let (opaque, mut translucent) = quads.into_iter().partition::<Vec<_>, _>(|quad| quad.colour[3] >= 1.0);
+    translucent.sort_by(|a, b| {
+        let depth = |quad : &Quad| quad.corners.iter().map(|c| c[2]).sum::<f32>();
+        depth(a).total_cmp(&depth(b))
+    });
+    let mut depths = vec![ f32::MIN; (options.size * options.size) as usize ];
+    for quad in &opaque {
+        fill_quad(&mut canvas, &mut depths, quad, true);
+    }
+    for quad in &translucent {
+        fill_quad(&mut canvas, &mut depths, quad, false);
+    }
+    Ok(canvas)
+}
+
+
+fn fill_quad(canvas : &mut RgbaImage, depths : &mut [f32], quad : &Quad, write_depth : bool) {
+    let [a, b, c, d] = quad.corners;
+    fill_triangle(canvas, depths, [a, b, c], quad.colour, write_depth);
+    fill_triangle(canvas, depths, [a, c, d], quad.colour, write_depth);
+}
+
+fn fill_triangle(canvas : &mut RgbaImage, depths : &mut [f32], [a, b, c] : [[f32; 3]; 3], colour : [f32; 4], write_depth : bool) {
+    let edge = |[ax, ay, _] : [f32; 3], [bx, by, _] : [f32; 3], [px, py] : [f32; 2]| (bx - ax) * (py - ay) - (by - ay) * (px - ax);
+    let area = edge(a, b, [c[0], c[1]]);
+    if (area.abs() <= f32::EPSILON) { return; }
+    let min_x = a[0].min(b[0]).min(c[0]).floor().max(0.0) as u32;
+    let min_y = a[1].min(b[1]).min(c[1]).floor().max(0.0) as u32;
+    let max_x = (a[0].max(b[0]).max(c[0]).ceil().max(0.0) as u32).min(canvas.width());
+    let max_y = (a[1].max(b[1]).max(c[1]).ceil().max(0.0) as u32).min(canvas.height());
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let p  = [ (x as f32) + 0.5, (y as f32) + 0.5 ];
+            let wa = edge(b, c, p) / area;
+            let wb = edge(c, a, p) / area;
+            let wc = edge(a, b, p) / area;
+            if (wa < -EDGE_TOLERANCE || wb < -EDGE_TOLERANCE || wc < -EDGE_TOLERANCE) { continue; }
+            let depth = wa * a[2] + wb * b[2] + wc * c[2];
+            let index = (y * canvas.width() + x) as usize;
+            // Overlapping overlay layers are coplanar; the part drawn first wins instead of z-fighting.
+            if (depth <= depths[index] + DEPTH_TOLERANCE) { continue; }
+            if (write_depth) { depths[index] = depth; }
+            blend(canvas.get_pixel_mut(x, y), colour);
+        }
+    }
+}
+
+fn blend(dst : &mut Rgba<u8>, [r, g, b, a] : [f32; 4]) {
+    let dst_a = (dst.0[3] as f32) / (u8::MAX as f32);
+    let out_a = a + dst_a * (1.0 - a);
+    if (out_a <= 0.0) { return; }
+    for (i, src) in [ r, g, b ].into_iter().enumerate() {
+        let dst_c = (dst.0[i] as f32) / (u8::MAX as f32);
+        dst.0[i] = (((src * a + dst_c * dst_a * (1.0 - a)) / out_a).clamp(0.0, 1.0) * (u8::MAX as f32)) as u8;
+    }
+    dst.0[3] = (out_a.clamp(0.0, 1.0) * (u8::MAX as f32)) as u8;
+}
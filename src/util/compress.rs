@@ -0,0 +1,64 @@
+use std::io::{ self, Write };
+use flate2::{
+    Compression,
+    write::GzEncoder
+};
+
+
+const BROTLI_QUALITY : u32 = 5;
+const BROTLI_WINDOW  : u32 = 22;
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    Gzip,
+    Brotli
+}
+
+impl Encoding {
+
+    pub fn as_str(self) -> &'static str {
+        match (self) {
+            Encoding::Gzip   => "gzip",
+            Encoding::Brotli => "br"
+        }
+    }
+
+    // Picks the preferred supported encoding from an `Accept-Encoding` header, favouring brotli on ties.
+    pub fn negotiate(accept_encoding : &str) -> Option<Self> {
+        let mut gzip     = None;
+        let mut brotli   = None;
+        let mut wildcard = None;
+        for coding in accept_encoding.split(',') {
+            let mut params = coding.split(';');
+            let     name   = params.next().unwrap_or_default().trim();
+            let     q      = params
+                .find_map(|param| param.trim().strip_prefix("q=").map(|q| q.trim().parse::<f32>().unwrap_or(0.0)))
+                .unwrap_or(1.0);
+            if (name.eq_ignore_ascii_case("gzip") || name.eq_ignore_ascii_case("x-gzip")) { gzip = Some(q); }
+            else if (name.eq_ignore_ascii_case("br")) { brotli = Some(q); }
+            else if (name == "*") { wildcard = Some(q); }
+        }
+        let brotli = brotli.or(wildcard).unwrap_or(0.0);
+        let gzip   = gzip.or(wildcard).unwrap_or(0.0);
+        if (brotli > 0.0 && brotli >= gzip) { Some(Encoding::Brotli) }
+        else if (gzip > 0.0) { Some(Encoding::Gzip) }
+        else { None }
+    }
+
+    pub fn compress(self, bytes : &[u8]) -> io::Result<Vec<u8>> {
+        match (self) {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            },
+            Encoding::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+                encoder.write_all(bytes)?;
+                Ok(encoder.into_inner())
+            }
+        }
+    }
+
+}
@@ -7,6 +7,12 @@ use std::{
 
 static mut DOTENV : LazyCell<HashMap<String, String>> = LazyCell::new(|| HashMap::new());
 
+// Tests run in parallel, so each one sets variables for its own thread instead of touching `DOTENV`.
+#[cfg(test)]
+thread_local! {
+    static OVERRIDES : std::cell::RefCell<HashMap<String, &'static str>> = std::cell::RefCell::new(HashMap::new());
+}
+
 
 pub unsafe fn load() {
     #[allow(static_mut_refs)]
@@ -27,12 +33,20 @@ pub unsafe fn load() {
 }
 
 
-// pub fn try_var(key : &str) -> Option<&'static str> {
-//     #[allow(static_mut_refs)]
-//     unsafe{ DOTENV.get(key).map(|s| s.as_str()) }
-// }
+pub fn try_var(key : &str) -> Option<&'static str> {
+    #[cfg(test)]
+    if let Some(value) = OVERRIDES.with_borrow(|overrides| overrides.get(key).copied()) {
+        return Some(value);
+    }
+    #[allow(static_mut_refs)]
+    unsafe{ DOTENV.get(key).map(|s| s.as_str()) }
+}
 
 pub fn var(key : &str) -> &'static str {
-    #[allow(static_mut_refs)]
-    unsafe{ DOTENV.get(key).expect("missing dotenv key").as_str() }
+    try_var(key).expect("missing dotenv key")
+}
+
+#[cfg(test)]
+pub fn set_for_test(key : &str, value : &str) {
+    OVERRIDES.with_borrow_mut(|overrides| { overrides.insert(key.to_string(), value.to_string().leak()); });
 }
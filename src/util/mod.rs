@@ -4,3 +4,6 @@ pub mod math;
 pub mod rand;
 
 pub mod image;
+pub mod ratelimit;
+
+pub mod compress;
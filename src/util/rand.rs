@@ -1,4 +1,8 @@
 use rand::CryptoRng;
+use base64::{
+    prelude::BASE64_STANDARD,
+    Engine
+};
 
 
 const CHARS : &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789-.";
@@ -22,3 +26,19 @@ fn gen_token_with<const LEN : usize>(rand : &mut impl CryptoRng) -> String {
 pub fn gen_token() -> String {
     gen_token_with::<256>(&mut rand::rng())
 }
+
+#[inline]
+pub fn gen_pkce_verifier() -> String {
+    gen_token_with::<128>(&mut rand::rng())
+}
+
+// Runs in time independent of where the first difference is, so comparing a guess against a secret doesn't leak it.
+pub fn constant_time_eq(a : &str, b : &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// CSP nonces must be base64, which rules out the `.` in `CHARS`.
+#[inline]
+pub fn gen_nonce() -> String {
+    BASE64_STANDARD.encode(gen_bytes_with::<16>(&mut rand::rng()))
+}
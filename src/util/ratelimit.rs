@@ -0,0 +1,171 @@
+use std::time::{ Duration, Instant };
+use tide::StatusCode;
+
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct UpstreamRateLimit {
+    pub limited     : bool,
+    pub remaining   : Option<u64>,
+    pub reset_after : Option<Duration>,
+    pub retry_after : Option<Duration>
+}
+
+impl UpstreamRateLimit {
+
+    pub fn from_response(response : &surf::Response) -> Self {
+        let header_u64 = |name : &str| response.header(name).and_then(|v| v.last().as_str().trim().parse::<u64>().ok());
+        Self {
+            limited     : response.status() == StatusCode::TooManyRequests,
+            remaining   : header_u64("X-RateLimit-Remaining"),
+            reset_after : header_u64("X-RateLimit-Reset").map(Duration::from_secs),
+            retry_after : header_u64("Retry-After").map(Duration::from_secs)
+        }
+    }
+
+}
+
+
+pub struct RateBudget {
+    capacity      : u64,
+    window        : Duration,
+    window_start  : Instant,
+    used          : u64,
+    blocked_until : Option<Instant>
+}
+
+impl RateBudget {
+
+    pub fn new(capacity : u64, window : Duration) -> Self {
+        Self {
+            capacity,
+            window,
+            window_start  : Instant::now(),
+            used          : 0,
+            blocked_until : None
+        }
+    }
+
+    fn roll_window(&mut self, now : Instant) {
+        if (now.duration_since(self.window_start) >= self.window) {
+            self.window_start = now;
+            self.used         = 0;
+        }
+    }
+
+    pub fn remaining(&mut self) -> u64 {
+        let now = Instant::now();
+        self.roll_window(now);
+        if (self.blocked_until.is_some_and(|until| now < until)) {
+            return 0;
+        }
+        self.capacity.saturating_sub(self.used)
+    }
+
+    pub fn retry_after(&mut self) -> Duration {
+        let now = Instant::now();
+        if let Some(until) = self.blocked_until && now < until {
+            return until - now;
+        }
+        if (self.remaining() > 0) { Duration::ZERO }
+        else { self.window.saturating_sub(now.duration_since(self.window_start)) }
+    }
+
+    // Takes all of the units or none of them.
+    pub fn try_acquire(&mut self, units : u64) -> bool {
+        if (self.remaining() < units) {
+            return false;
+        }
+        self.used += units;
+        true
+    }
+
+    pub fn record(&mut self, upstream : &UpstreamRateLimit) {
+        let now = Instant::now();
+        if let Some(remaining) = upstream.remaining {
+            self.used = self.used.max(self.capacity.saturating_sub(remaining));
+        }
+        let wait = if (upstream.limited) {
+            Some(upstream.retry_after.or(upstream.reset_after).unwrap_or(self.window))
+        } else if (upstream.remaining == Some(0)) {
+            Some(upstream.reset_after.unwrap_or(self.window))
+        } else { None };
+        if let Some(wait) = wait {
+            self.blocked_until = Some(now + wait);
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upstream(status : StatusCode, headers : &[(&str, &str)]) -> UpstreamRateLimit {
+        let mut response = tide::http::Response::new(status);
+        for &(name, value) in headers {
+            response.insert_header(name, value);
+        }
+        UpstreamRateLimit::from_response(&surf::Response::from(response))
+    }
+
+    #[test]
+    fn reads_upstream_headers() {
+        let limit = upstream(StatusCode::TooManyRequests, &[ ("Retry-After", "30"), ("X-RateLimit-Remaining", "0"), ("X-RateLimit-Reset", "45") ]);
+        assert!(limit.limited);
+        assert_eq!(limit.remaining, Some(0));
+        assert_eq!(limit.reset_after, Some(Duration::from_secs(45)));
+        assert_eq!(limit.retry_after, Some(Duration::from_secs(30)));
+        let limit = upstream(StatusCode::Ok, &[ ("X-RateLimit-Remaining", "not a number") ]);
+        assert!((! limit.limited) && limit.remaining.is_none() && limit.retry_after.is_none());
+    }
+
+    #[test]
+    fn exhausts_and_refills() {
+        let mut budget = RateBudget::new(2, Duration::from_millis(50));
+        assert!(budget.try_acquire(1));
+        assert!(budget.try_acquire(1));
+        assert!(! budget.try_acquire(1));
+        assert!(budget.retry_after() > Duration::ZERO && budget.retry_after() <= Duration::from_millis(50));
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(budget.remaining(), 2);
+        assert!(budget.try_acquire(1));
+    }
+
+    #[test]
+    fn takes_all_units_or_none() {
+        let mut budget = RateBudget::new(3, Duration::from_secs(60));
+        assert!(! budget.try_acquire(4));
+        assert_eq!(budget.remaining(), 3);
+        assert!(budget.try_acquire(3));
+        assert_eq!(budget.remaining(), 0);
+    }
+
+    #[test]
+    fn blocks_after_upstream_429() {
+        let mut budget = RateBudget::new(100, Duration::from_secs(60));
+        budget.record(&upstream(StatusCode::TooManyRequests, &[ ("Retry-After", "30") ]));
+        assert_eq!(budget.remaining(), 0);
+        assert!(! budget.try_acquire(1));
+        let retry_after = budget.retry_after();
+        assert!(retry_after > Duration::from_secs(29) && retry_after <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn blocks_when_upstream_has_none_left() {
+        let mut budget = RateBudget::new(100, Duration::from_secs(60));
+        budget.record(&upstream(StatusCode::Ok, &[ ("X-RateLimit-Remaining", "0"), ("X-RateLimit-Reset", "10") ]));
+        assert_eq!(budget.remaining(), 0);
+        assert!(budget.retry_after() <= Duration::from_secs(10));
+    }
+
+    #[test]
+    fn follows_upstream_remaining() {
+        let mut budget = RateBudget::new(100, Duration::from_secs(60));
+        budget.record(&upstream(StatusCode::Ok, &[ ("X-RateLimit-Remaining", "40") ]));
+        assert_eq!(budget.remaining(), 40);
+        // A lower local count never raises what upstream says is left.
+        budget.record(&upstream(StatusCode::Ok, &[ ("X-RateLimit-Remaining", "90") ]));
+        assert_eq!(budget.remaining(), 40);
+    }
+}